@@ -0,0 +1,143 @@
+use crate::constants::DEFAULT_IDENTITY_PATH;
+use crate::core::checkpoint::Checkpoints;
+use crate::core::config::ChainConfig;
+use crate::core::export::ArchiveFormat;
+use crate::core::mining::NonceMode;
+
+pub fn parse_file_args<'a>(args: &[&'a str]) -> Result<(&'a str, ArchiveFormat), String> {
+    let mut path = None;
+    let mut format = None;
+    let mut iter = args.iter();
+
+    while let Some(&arg) = iter.next() {
+        if arg == "--format" {
+            let name = iter.next().ok_or("'--format' requer um valor")?;
+            format = Some(
+                ArchiveFormat::from_name(name).ok_or(format!("Formato desconhecido: '{name}'"))?,
+            );
+        } else if path.is_none() {
+            path = Some(arg);
+        } else {
+            return Err(format!("Argumento desconhecido: '{arg}'"));
+        }
+    }
+
+    let path = path.ok_or("Nenhum arquivo especificado")?;
+    let format = format
+        .or_else(|| ArchiveFormat::from_path(path))
+        .ok_or(format!(
+            "Não foi possível deduzir o formato de '{path}'; use --format"
+        ))?;
+
+    Ok((path, format))
+}
+
+pub fn take_chain_config<'a>(args: &[&'a str]) -> Result<(ChainConfig, Vec<&'a str>), String> {
+    let mut config_path = None;
+    let mut fixed_difficulty = None;
+    let mut rest = Vec::new();
+    let mut iter = args.iter();
+
+    while let Some(&arg) = iter.next() {
+        match arg {
+            "--config" => {
+                config_path = Some(*iter.next().ok_or("'--config' requer um arquivo")?);
+            }
+            "--difficulty" => {
+                fixed_difficulty = Some(*iter.next().ok_or("'--difficulty' requer um valor")?);
+            }
+            _ => rest.push(arg),
+        }
+    }
+
+    let mut config = match config_path {
+        Some(path) => ChainConfig::load(path)?,
+        None => ChainConfig::default(),
+    };
+
+    if let Some(value) = fixed_difficulty {
+        config.set_fixed_difficulty(value)?;
+    }
+
+    config.validate()?;
+    Ok((config, rest))
+}
+
+pub fn take_checkpoints<'a>(args: &[&'a str]) -> Result<(Checkpoints, Vec<&'a str>), String> {
+    let mut checkpoints = Checkpoints::new();
+    let mut rest = Vec::new();
+    let mut iter = args.iter();
+
+    while let Some(&arg) = iter.next() {
+        match arg {
+            "--checkpoints" => {
+                let path = iter.next().ok_or("'--checkpoints' requer um arquivo")?;
+                checkpoints = Checkpoints::load(path)?;
+            }
+            _ => rest.push(arg),
+        }
+    }
+
+    Ok((checkpoints, rest))
+}
+
+pub struct NodeOptions<'a> {
+    pub config: ChainConfig,
+    pub identity_path: &'a str,
+    pub initial_peer: Option<&'a str>,
+    pub miner: bool,
+    pub nonce_mode: NonceMode,
+    pub fixed_timestamp: Option<u64>,
+    pub checkpoints: Checkpoints,
+}
+
+impl<'a> NodeOptions<'a> {
+    pub fn parse(args: &[&'a str]) -> Result<Self, String> {
+        let (config, rest) = take_chain_config(args)?;
+        let (checkpoints, rest) = take_checkpoints(&rest)?;
+        let mut identity_path = DEFAULT_IDENTITY_PATH;
+        let mut initial_peer = None;
+        let mut miner = false;
+        let mut nonce_mode = NonceMode::default();
+        let mut fixed_timestamp = None;
+        let mut iter = rest.into_iter();
+
+        while let Some(arg) = iter.next() {
+            match arg {
+                "--identity" => {
+                    identity_path = iter.next().ok_or("'--identity' requer um arquivo")?;
+                }
+                "--miner" => miner = true,
+                "--nonce" => {
+                    let name = iter.next().ok_or("'--nonce' requer um modo")?;
+                    nonce_mode = NonceMode::from_name(name).ok_or(format!(
+                        "Modo de nonce desconhecido: '{name}' (use random, sequential ou seed:<n>)"
+                    ))?;
+                }
+                "--timestamp" => {
+                    let value = iter.next().ok_or("'--timestamp' requer um horário Unix")?;
+                    fixed_timestamp = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("Horário Unix inválido: '{value}'"))?,
+                    );
+                }
+                _ if arg.starts_with("--") => {
+                    return Err(format!("Opção desconhecida: '{arg}'"));
+                }
+                _ if initial_peer.is_none() => initial_peer = Some(arg),
+                _ => return Err(format!("Argumento desconhecido: '{arg}'")),
+            }
+        }
+
+        Ok(NodeOptions {
+            config,
+            identity_path,
+            initial_peer,
+            miner,
+            nonce_mode,
+            fixed_timestamp,
+            checkpoints,
+        })
+    }
+}
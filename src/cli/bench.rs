@@ -0,0 +1,227 @@
+use super::args::take_chain_config;
+use crate::core::archive::Archive;
+use crate::core::config::ChainConfig;
+use crate::core::difficulty::meets_difficulty;
+use crate::core::identity::Identity;
+use crate::core::message::{Chat, DEFAULT_CHANNEL};
+use crate::core::mining::format_hashrate;
+use rand::Rng;
+use std::hint;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const DEFAULT_SECONDS: u64 = 3;
+const WINDOW_CHATS: usize = 19;
+const SAMPLE_MESSAGE: &str = "Mensagem de exemplo usada para medir a taxa de mineração do nó";
+const REPORTED_DIFFICULTIES: [u32; 7] = [8, 12, 16, 20, 24, 28, 32];
+
+struct BenchOptions {
+    config: ChainConfig,
+    threads: Vec<usize>,
+    seconds: u64,
+}
+
+impl BenchOptions {
+    fn parse(args: &[&str]) -> Result<Self, String> {
+        let (config, rest) = take_chain_config(args)?;
+        let mut threads = None;
+        let mut seconds = DEFAULT_SECONDS;
+        let mut iter = rest.into_iter();
+
+        while let Some(arg) = iter.next() {
+            match arg {
+                "-t" | "--threads" => {
+                    let value = iter.next().ok_or(format!("'{arg}' requer uma lista"))?;
+                    let counts = value
+                        .split(',')
+                        .map(|count| match count.parse() {
+                            Ok(count) if count > 0 => Ok(count),
+                            _ => Err(format!("Número de threads inválido: '{count}'")),
+                        })
+                        .collect::<Result<Vec<usize>, String>>()?;
+                    threads = Some(counts);
+                }
+                "-s" | "--seconds" => {
+                    let value = iter.next().ok_or(format!("'{arg}' requer um número"))?;
+                    seconds = match value.parse() {
+                        Ok(seconds) if seconds > 0 => seconds,
+                        _ => return Err(format!("Duração inválida: '{value}'")),
+                    };
+                }
+                _ => return Err(format!("Argumento desconhecido: '{arg}'")),
+            }
+        }
+
+        Ok(BenchOptions {
+            config,
+            threads: threads.unwrap_or_else(default_thread_counts),
+            seconds,
+        })
+    }
+}
+
+fn default_thread_counts() -> Vec<usize> {
+    let available = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut counts: Vec<usize> = (0..)
+        .map(|exp| 1 << exp)
+        .take_while(|&count| count < available)
+        .collect();
+    counts.push(available);
+    counts
+}
+
+pub fn run(args: &[&str]) -> i32 {
+    let options = match BenchOptions::parse(args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}");
+            eprintln!(
+                "Uso: bench [--threads 1,2,4] [--seconds <n>] [--config <arquivo>] [--difficulty <bits>]"
+            );
+            return 2;
+        }
+    };
+
+    let (window, chat) = sample_input(options.config);
+    println!(
+        "Medindo mineração {} ({} bytes de janela + {} bytes de registro) por {}s em cada configuração...",
+        options.config.hash_function.name(),
+        window.len(),
+        chat.hashed_bytes().len(),
+        options.seconds
+    );
+
+    let mut results = Vec::new();
+    for &threads in &options.threads {
+        let hashrate = measure(
+            options.config,
+            &window,
+            &chat,
+            threads,
+            Duration::from_secs(options.seconds),
+        );
+        println!("  {threads:>3} thread(s): {}", format_hashrate(hashrate));
+        results.push((threads, hashrate));
+    }
+
+    let single = results
+        .iter()
+        .find(|(threads, _)| *threads == 1)
+        .map(|&(_, hashrate)| hashrate);
+    let (best_threads, best) = results
+        .iter()
+        .copied()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or((1, 0.0));
+
+    let max_bits = options.config.hash_function.digest_len() as u32 * 8;
+    let mut difficulties: Vec<u32> = REPORTED_DIFFICULTIES
+        .into_iter()
+        .chain([options.config.difficulty_bits])
+        .filter(|&bits| bits <= max_bits)
+        .collect();
+    difficulties.sort_unstable();
+    difficulties.dedup();
+
+    println!("\nTempo esperado por mensagem (2^bits tentativas):");
+    for bits in difficulties {
+        let expected = 2f64.powi(bits as i32);
+        let marker = if bits == options.config.difficulty_bits {
+            " (configurada)"
+        } else {
+            ""
+        };
+        let single = single
+            .filter(|_| best_threads != 1)
+            .map(|hashrate| format!("{} com 1 thread, ", expected_time(expected, hashrate)))
+            .unwrap_or_default();
+        println!(
+            "  {bits:>3} bits: {single}{} com {best_threads} thread(s){marker}",
+            expected_time(expected, best)
+        );
+    }
+    println!("\nO nó minera cada mensagem em uma única thread.");
+
+    0
+}
+
+fn sample_input(config: ChainConfig) -> (Vec<u8>, Chat) {
+    let identity = Identity::generate();
+    let mut archive = Archive::new(config);
+    let hash_len = config.hash_function.digest_len();
+    let mut rng = rand::rng();
+
+    for _ in 0..WINDOW_CHATS {
+        let mut chat = archive
+            .prepare_message(SAMPLE_MESSAGE.to_string(), DEFAULT_CHANNEL, &identity)
+            .expect("mensagem de exemplo válida");
+        rng.fill(&mut chat.verification_code);
+        chat.hash = (0..hash_len).map(|_| rng.random()).collect();
+        archive.chats.push(chat);
+    }
+
+    let chat = archive
+        .prepare_message(SAMPLE_MESSAGE.to_string(), DEFAULT_CHANNEL, &identity)
+        .expect("mensagem de exemplo válida");
+    (archive.hash_window(archive.len()), chat)
+}
+
+fn measure(
+    config: ChainConfig,
+    window: &[u8],
+    chat: &Chat,
+    threads: usize,
+    duration: Duration,
+) -> f64 {
+    let stop = AtomicBool::new(false);
+    let started = Instant::now();
+
+    let attempts: u64 = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                let stop = &stop;
+                let mut chat = chat.clone();
+                scope.spawn(move || {
+                    let hasher = config.hash_function.hasher();
+                    let mut rng = rand::rng();
+                    let mut attempts = 0u64;
+                    while !stop.load(Ordering::Relaxed) {
+                        rng.fill(&mut chat.verification_code);
+                        let mut data_to_hash = window.to_vec();
+                        data_to_hash.extend_from_slice(&chat.hashed_bytes());
+                        let hash = hasher.digest(&data_to_hash);
+                        hint::black_box(meets_difficulty(&hash, config.difficulty_bits));
+                        attempts += 1;
+                    }
+                    attempts
+                })
+            })
+            .collect();
+
+        thread::sleep(duration);
+        stop.store(true, Ordering::Relaxed);
+        workers.into_iter().map(|w| w.join().unwrap()).sum()
+    });
+
+    attempts as f64 / started.elapsed().as_secs_f64()
+}
+
+fn expected_time(attempts: f64, hashrate: f64) -> String {
+    if hashrate <= 0.0 {
+        return "?".to_string();
+    }
+
+    let secs = attempts / hashrate;
+    if secs < 1.0 {
+        format!("{:.0} ms", secs * 1e3)
+    } else if secs < 60.0 {
+        format!("{secs:.1} s")
+    } else if secs < 3600.0 {
+        format!("{:.1} min", secs / 60.0)
+    } else if secs < 86400.0 {
+        format!("{:.1} h", secs / 3600.0)
+    } else {
+        format!("{:.1} dias", secs / 86400.0)
+    }
+}
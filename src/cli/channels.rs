@@ -0,0 +1,83 @@
+use super::console;
+use crate::core::archive::Archive;
+use crate::core::message::DEFAULT_CHANNEL;
+use std::collections::BTreeSet;
+
+pub struct ChannelState {
+    current: String,
+    joined: BTreeSet<String>,
+}
+
+impl ChannelState {
+    pub fn new() -> Self {
+        console::set_prompt_channel(DEFAULT_CHANNEL);
+        ChannelState {
+            current: DEFAULT_CHANNEL.to_string(),
+            joined: BTreeSet::from([DEFAULT_CHANNEL.to_string()]),
+        }
+    }
+
+    pub fn current(&self) -> &str {
+        &self.current
+    }
+
+    pub fn is_joined(&self, channel: &str) -> bool {
+        self.joined.contains(channel)
+    }
+
+    pub fn join(&mut self, name: &str) -> Result<String, String> {
+        let channel = normalize(name)?;
+        self.joined.insert(channel.clone());
+        self.current = channel.clone();
+        console::set_prompt_channel(&channel);
+        Ok(channel)
+    }
+
+    pub fn leave(&mut self, name: &str) -> Result<String, String> {
+        let channel = normalize(name)?;
+        if channel == DEFAULT_CHANNEL {
+            return Err(format!("Não é possível sair do canal #{DEFAULT_CHANNEL}."));
+        }
+        if !self.joined.remove(&channel) {
+            return Err(format!("Você não está no canal #{channel}."));
+        }
+
+        if self.current == channel {
+            self.current = DEFAULT_CHANNEL.to_string();
+            console::set_prompt_channel(DEFAULT_CHANNEL);
+        }
+        Ok(channel)
+    }
+
+    pub fn print(&self, archive: &Archive) {
+        let counts = archive.channels();
+        let mut names: BTreeSet<&str> = counts.keys().map(String::as_str).collect();
+        names.extend(self.joined.iter().map(String::as_str));
+
+        println!("--- Canais ({}) ---", names.len());
+        for name in names {
+            let marker = if name == self.current {
+                "*"
+            } else if self.is_joined(name) {
+                "+"
+            } else {
+                " "
+            };
+            let count = counts.get(name).copied().unwrap_or(0);
+            println!("{marker} #{name} ({count} mensagens)");
+        }
+        println!("-------------------");
+        println!("* canal atual, + canais em que você está");
+    }
+}
+
+pub fn normalize(name: &str) -> Result<String, String> {
+    let channel = name.trim_start_matches('#').to_ascii_lowercase();
+    if Archive::is_valid_channel_name(&channel) {
+        Ok(channel)
+    } else {
+        Err(format!(
+            "Nome de canal inválido: '{name}'. Use de 1 a 24 letras, dígitos, '_' ou '-'."
+        ))
+    }
+}
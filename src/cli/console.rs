@@ -0,0 +1,53 @@
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static MUTED: AtomicBool = AtomicBool::new(false);
+static PROMPT_CHANNEL: Mutex<String> = Mutex::new(String::new());
+static STDOUT_LOCK: Mutex<()> = Mutex::new(());
+
+pub fn set_muted(muted: bool) {
+    MUTED.store(muted, Ordering::SeqCst);
+}
+
+pub fn is_muted() -> bool {
+    MUTED.load(Ordering::SeqCst)
+}
+
+pub fn set_prompt_channel(channel: &str) {
+    *PROMPT_CHANNEL.lock().unwrap() = channel.to_string();
+}
+
+fn prompt() -> String {
+    let channel = PROMPT_CHANNEL.lock().unwrap();
+    if channel.is_empty() {
+        "> ".to_string()
+    } else {
+        format!("#{channel}> ")
+    }
+}
+
+pub fn print_prompt() {
+    let _guard = STDOUT_LOCK.lock().unwrap();
+    let mut stdout = io::stdout();
+    let _ = write!(stdout, "{}", prompt());
+    let _ = stdout.flush();
+}
+
+pub fn print_async(line: &str) {
+    if is_muted() {
+        return;
+    }
+
+    let _guard = STDOUT_LOCK.lock().unwrap();
+    let mut stdout = io::stdout();
+    let _ = write!(stdout, "\r\x1b[2K{line}\n{}", prompt());
+    let _ = stdout.flush();
+}
+
+pub fn print_status(line: &str) {
+    let _guard = STDOUT_LOCK.lock().unwrap();
+    let mut stdout = io::stdout();
+    let _ = write!(stdout, "\r\x1b[2K{line}");
+    let _ = stdout.flush();
+}
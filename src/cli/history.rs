@@ -0,0 +1,337 @@
+use super::channels;
+use crate::core::archive::Archive;
+use crate::core::direct;
+use crate::core::hex::to_hex;
+use crate::core::identity::{Identity, fingerprint};
+use crate::core::message::{Chat, ChatKind, DEFAULT_CHANNEL};
+use chrono::{DateTime, Local};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Range;
+
+const MAX_THREAD_INDENT: usize = 8;
+
+#[derive(Debug, Default)]
+pub struct HistoryOptions {
+    pub range: Option<(Option<usize>, Option<usize>)>,
+    pub last: Option<usize>,
+    pub channel: Option<String>,
+    pub thread: Option<usize>,
+    pub show_retracted: bool,
+    pub verbose: bool,
+}
+
+impl HistoryOptions {
+    pub fn parse(args: &[&str]) -> Result<Self, String> {
+        let mut options = HistoryOptions::default();
+        let mut iter = args.iter();
+
+        while let Some(&arg) = iter.next() {
+            match arg {
+                "-v" | "--verbose" => options.verbose = true,
+                "-r" | "--retracted" => options.show_retracted = true,
+                "-l" | "--last" => {
+                    let value = iter
+                        .next()
+                        .ok_or_else(|| format!("'{arg}' requer um número"))?;
+                    let last = value
+                        .parse()
+                        .map_err(|_| format!("Quantidade inválida: '{value}'"))?;
+                    options.last = Some(last);
+                }
+                "-t" | "--thread" => {
+                    let value = iter
+                        .next()
+                        .ok_or_else(|| format!("'{arg}' requer um índice"))?;
+                    let index = value
+                        .parse()
+                        .map_err(|_| format!("Índice inválido: '{value}'"))?;
+                    options.thread = Some(index);
+                }
+                "-c" | "--channel" => {
+                    let value = iter
+                        .next()
+                        .ok_or_else(|| format!("'{arg}' requer um nome de canal"))?;
+                    options.channel = Some(channels::normalize(value)?);
+                }
+                _ if arg.contains("..") => options.range = Some(parse_range(arg)?),
+                _ => return Err(format!("Argumento desconhecido: '{arg}'")),
+            }
+        }
+
+        Ok(options)
+    }
+
+    pub fn select(&self, archive: &Archive) -> Vec<usize> {
+        let Some(channel) = &self.channel else {
+            return self.resolve(archive.len()).collect();
+        };
+
+        let unlimited = HistoryOptions {
+            last: None,
+            channel: None,
+            thread: None,
+            ..*self
+        };
+        let selected: Vec<usize> = unlimited
+            .resolve(archive.len())
+            .filter(|&i| archive.chats[i].channel() == channel)
+            .collect();
+        let skip = selected
+            .len()
+            .saturating_sub(self.last.unwrap_or(usize::MAX));
+        selected[skip..].to_vec()
+    }
+
+    pub fn resolve(&self, len: usize) -> Range<usize> {
+        let (mut start, mut end) = match self.range {
+            Some((start, end)) => (start.unwrap_or(0), end.unwrap_or(len)),
+            None => (0, len),
+        };
+
+        end = end.min(len);
+        start = start.min(end);
+
+        if let Some(last) = self.last {
+            start = start.max(end.saturating_sub(last));
+        }
+
+        start..end
+    }
+}
+
+fn parse_range(arg: &str) -> Result<(Option<usize>, Option<usize>), String> {
+    let (start, end) = arg.split_once("..").unwrap();
+    let parse_bound = |bound: &str| -> Result<Option<usize>, String> {
+        if bound.is_empty() {
+            Ok(None)
+        } else {
+            bound
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("Intervalo inválido: '{arg}'"))
+        }
+    };
+
+    let (start, end) = (parse_bound(start)?, parse_bound(end)?);
+    if let (Some(s), Some(e)) = (start, end)
+        && s > e
+    {
+        return Err(format!("Intervalo inválido: '{arg}'"));
+    }
+
+    Ok((start, end))
+}
+
+pub fn format_timestamp(timestamp: Option<u64>) -> String {
+    timestamp
+        .and_then(|secs| DateTime::from_timestamp(secs as i64, 0))
+        .map(|time| {
+            time.with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or_else(|| "-------------------".to_string())
+}
+
+fn channel_prefix(chat: &Chat) -> String {
+    if chat.channel() == DEFAULT_CHANNEL {
+        String::new()
+    } else {
+        format!("#{} ", chat.channel())
+    }
+}
+
+pub struct HistoryView<'a> {
+    archive: &'a Archive,
+    identity: &'a Identity,
+    nicknames: HashMap<[u8; 32], String>,
+    replies: HashMap<usize, Vec<usize>>,
+    reactions: HashMap<usize, BTreeMap<String, usize>>,
+    retracted: HashSet<usize>,
+    show_retracted: bool,
+}
+
+impl<'a> HistoryView<'a> {
+    pub fn new(archive: &'a Archive, identity: &'a Identity) -> Self {
+        let retracted = archive.retracted();
+        let mut replies: HashMap<usize, Vec<usize>> = HashMap::new();
+        let mut reactions: HashMap<usize, BTreeMap<String, usize>> = HashMap::new();
+        for (index, chat) in archive.chats.iter().enumerate() {
+            match &chat.kind {
+                ChatKind::Reaction(_) if retracted.contains(&index) => {}
+                ChatKind::Reply(reference) => {
+                    replies
+                        .entry(reference.index as usize)
+                        .or_default()
+                        .push(index);
+                }
+                ChatKind::Reaction(reference) => {
+                    *reactions
+                        .entry(reference.index as usize)
+                        .or_default()
+                        .entry(chat.message.clone())
+                        .or_insert(0) += 1;
+                }
+                _ => {}
+            }
+        }
+
+        HistoryView {
+            archive,
+            identity,
+            nicknames: archive.nicknames(),
+            replies,
+            reactions,
+            retracted,
+            show_retracted: false,
+        }
+    }
+
+    pub fn show_retracted(mut self, show: bool) -> Self {
+        self.show_retracted = show;
+        self
+    }
+
+    pub fn author_name(&self, chat: &Chat) -> String {
+        match &chat.author {
+            Some(author) => self.key_name(&author.public_key),
+            None => "anônimo".to_string(),
+        }
+    }
+
+    fn key_name(&self, public_key: &[u8; 32]) -> String {
+        self.nicknames
+            .get(public_key)
+            .cloned()
+            .unwrap_or_else(|| fingerprint(public_key))
+    }
+
+    pub fn render(&self, index: usize, chat: &Chat) -> String {
+        if !self.retracted.contains(&index) {
+            return self.render_content(chat);
+        }
+
+        if self.show_retracted {
+            format!("{} [retirada]", self.render_content(chat))
+        } else {
+            format!(
+                "{}<{}> [mensagem retirada]",
+                channel_prefix(chat),
+                self.author_name(chat)
+            )
+        }
+    }
+
+    fn render_content(&self, chat: &Chat) -> String {
+        let channel = channel_prefix(chat);
+
+        match &chat.kind {
+            ChatKind::Text => format!("{channel}<{}> {}", self.author_name(chat), chat.message),
+            ChatKind::Reply(reference) => format!(
+                "{channel}<{}> ↳ [{}] {}",
+                self.author_name(chat),
+                reference.index,
+                chat.message
+            ),
+            ChatKind::Reaction(reference) => format!(
+                "* {} reagiu com {} a [{}]",
+                self.author_name(chat),
+                chat.message,
+                reference.index
+            ),
+            ChatKind::Retraction(reference) => format!(
+                "* {} retirou a mensagem [{}]",
+                self.author_name(chat),
+                reference.index
+            ),
+            ChatKind::Attachment(manifest) => format!(
+                "{channel}<{}> [anexo {}] {} ({} bytes)",
+                self.author_name(chat),
+                manifest.id(),
+                manifest.name,
+                manifest.size
+            ),
+            ChatKind::Genesis => format!("* registro gênese: {}", chat.message),
+            ChatKind::Nick => match &chat.author {
+                Some(author) => format!(
+                    "* {} registrou o apelido '{}'",
+                    fingerprint(&author.public_key),
+                    chat.message
+                ),
+                None => format!("* registro de apelido '{}' sem autor", chat.message),
+            },
+            ChatKind::Direct(direct) => {
+                let Some(author) = &chat.author else {
+                    return "[mensagem direta cifrada]".to_string();
+                };
+                match direct::decrypt(self.identity, &author.public_key, direct) {
+                    Some(text) if self.archive.validate_message(&text, chat.version).is_err() => {
+                        "[mensagem direta inválida]".to_string()
+                    }
+                    Some(text) => format!(
+                        "<{} → {}> [DM] {text}",
+                        self.key_name(&author.public_key),
+                        self.key_name(&direct.recipient)
+                    ),
+                    None => "[mensagem direta cifrada]".to_string(),
+                }
+            }
+        }
+    }
+
+    pub fn print_chat(&self, index: usize, chat: &Chat, width: usize, verbose: bool) {
+        if matches!(chat.kind, ChatKind::Reaction(_) | ChatKind::Retraction(_)) && !verbose {
+            return;
+        }
+
+        self.print_line(index, chat, width, verbose, "");
+    }
+
+    pub fn print_thread(&self, index: usize, width: usize, verbose: bool) {
+        let mut root = index;
+        while let ChatKind::Reply(reference) = &self.archive.chats[root].kind {
+            root = reference.index as usize;
+        }
+
+        let mut stack = vec![(root, 0)];
+        while let Some((index, depth)) = stack.pop() {
+            let indent = "    ".repeat(depth.min(MAX_THREAD_INDENT));
+            self.print_line(index, &self.archive.chats[index], width, verbose, &indent);
+            for &reply in self.replies.get(&index).into_iter().flatten().rev() {
+                stack.push((reply, depth + 1));
+            }
+        }
+    }
+
+    fn print_line(&self, index: usize, chat: &Chat, width: usize, verbose: bool, indent: &str) {
+        println!(
+            "{indent}[{:0w$}] {} {}",
+            index,
+            format_timestamp(chat.timestamp),
+            self.render(index, chat),
+            w = width
+        );
+
+        let replies = self.replies.get(&index).map_or(0, Vec::len);
+        let reactions = self.reactions.get(&index);
+        if replies > 0 || reactions.is_some() {
+            let mut summary: Vec<String> = reactions
+                .into_iter()
+                .flatten()
+                .map(|(reaction, count)| format!("{reaction} {count}"))
+                .collect();
+            if replies > 0 {
+                summary.push(format!("{replies} resposta(s)"));
+            }
+            println!("{indent}    {}", summary.join("  "));
+        }
+
+        if verbose {
+            println!("{indent}    código: {}", to_hex(&chat.verification_code));
+            println!("{indent}    hash:   {}", to_hex(&chat.hash));
+            if let Some(author) = &chat.author {
+                println!("{indent}    autor:  {}", to_hex(&author.public_key));
+            }
+        }
+    }
+}
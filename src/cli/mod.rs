@@ -0,0 +1,7 @@
+pub mod args;
+pub mod bench;
+pub mod channels;
+pub mod console;
+pub mod history;
+pub mod progress;
+pub mod verify;
@@ -0,0 +1,76 @@
+use super::console;
+use crate::core::mining::{self, MiningProgress, format_hashrate};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+pub struct MiningIndicator {
+    label: Arc<Mutex<String>>,
+    done: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MiningIndicator {
+    pub fn start() -> Self {
+        let label = Arc::new(Mutex::new(String::new()));
+        let done = Arc::new(AtomicBool::new(false));
+        let thread_label = Arc::clone(&label);
+        let thread_done = Arc::clone(&done);
+
+        let handle = thread::spawn(move || {
+            let mut shown = false;
+            while !thread_done.load(Ordering::SeqCst) {
+                if let Some(progress) = mining::current() {
+                    let label = thread_label.lock().unwrap().clone();
+                    console::print_status(&format!("{label}{}", describe(&progress)));
+                    shown = true;
+                }
+                thread::park_timeout(REFRESH_INTERVAL);
+            }
+            if shown {
+                console::print_status("");
+            }
+        });
+
+        MiningIndicator {
+            label,
+            done,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn set_label(&self, label: String) {
+        *self.label.lock().unwrap() = label;
+    }
+}
+
+impl Drop for MiningIndicator {
+    fn drop(&mut self) {
+        self.done.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            handle.thread().unpark();
+            let _ = handle.join();
+        }
+    }
+}
+
+pub fn with_indicator<T>(mine: impl FnOnce() -> T) -> T {
+    let _indicator = MiningIndicator::start();
+    mine()
+}
+
+pub fn describe(progress: &MiningProgress) -> String {
+    let eta = progress
+        .eta()
+        .map(|eta| format!("~{}s", eta.as_secs()))
+        .unwrap_or_else(|| "?".to_string());
+    format!(
+        "Minerando ({} bits): {} tentativas, {}, tempo esperado {eta}",
+        progress.difficulty_bits,
+        progress.attempts,
+        format_hashrate(progress.hashrate())
+    )
+}
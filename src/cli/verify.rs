@@ -0,0 +1,73 @@
+use super::args::{parse_file_args, take_chain_config, take_checkpoints};
+use crate::core::archive::ValidationError;
+use crate::core::export;
+use crate::core::hex::to_hex;
+use std::fs;
+
+pub fn run(args: &[&str]) -> i32 {
+    let parsed = take_chain_config(args).and_then(|(config, rest)| {
+        let (checkpoints, rest) = take_checkpoints(&rest)?;
+        parse_file_args(&rest).map(|file| (config, checkpoints, file))
+    });
+
+    let (config, checkpoints, (path, format)) = match parsed {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{e}");
+            eprintln!(
+                "Uso: verify <arquivo> [--format json|csv|raw] [--config <arquivo>] [--difficulty <bits>] [--checkpoints <arquivo>]"
+            );
+            return 2;
+        }
+    };
+
+    let mut archive = match fs::read(path)
+        .map_err(export::ArchiveFileError::from)
+        .and_then(|data| export::decode_archive(&data, format, config))
+    {
+        Ok(archive) => archive,
+        Err(e) => {
+            eprintln!("Erro ao ler '{path}': {e}");
+            return 2;
+        }
+    };
+
+    archive.checkpoints = checkpoints;
+    println!("Verificando '{path}' ({} mensagens)...", archive.len());
+
+    if let Err(e) = archive.validate() {
+        let index = e.index();
+        match archive.chats.get(index) {
+            Some(chat) => {
+                println!("Mensagem inválida no índice {index}:");
+                println!("  mensagem: {:?}", chat.message);
+            }
+            None => println!("Arquivo inválido:"),
+        }
+        match e {
+            ValidationError::BadPrefix {
+                required_bits,
+                actual,
+                ..
+            } => {
+                println!("  motivo: hash não começa com {required_bits} bits zero");
+                println!("  hash obtido:   {}", to_hex(&actual));
+            }
+            ValidationError::HashMismatch {
+                expected, actual, ..
+            } => {
+                println!("  motivo: hash não corresponde ao conteúdo");
+                println!("  hash esperado: {}", to_hex(&expected));
+                println!("  hash obtido:   {}", to_hex(&actual));
+            }
+            ValidationError::InvalidMessage { error, .. } => {
+                println!("  motivo: {error}");
+            }
+            other => println!("  motivo: {other}"),
+        }
+        return 1;
+    }
+
+    println!("Arquivo válido: {} mensagens verificadas.", archive.len());
+    0
+}
@@ -1 +1,3 @@
 pub const TCP_PORT: u16 = 51511;
+pub const DEFAULT_IDENTITY_PATH: &str = ".p2p_chat/identity.key";
+pub const DEFAULT_CHUNK_DIR: &str = ".p2p_chat/chunks";
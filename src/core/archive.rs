@@ -1,16 +1,437 @@
 use crate::logger;
 
-use super::message::{Chat, MessageType};
-use rand::{self, Rng};
+use super::checkpoint::Checkpoints;
+use super::config::{ChainConfig, LEGACY_MAX_MESSAGE_BYTES};
+use super::difficulty::{meets_difficulty, retarget, work_for_hash};
+use super::direct::{self, DIRECT_OVERHEAD};
+use super::hasher::HashFunction;
+use super::hex::{from_hex, to_hex};
+use super::identity::{Identity, fingerprint, verify_signature};
+use super::message::{
+    AttachmentManifest, Author, CURRENT_CHAT_VERSION, Chat, ChatKind, ChatReference,
+    DEFAULT_CHANNEL, GENESIS_CHAT_VERSION, MAX_ATTACHMENT_NAME_LEN, MessageType,
+    UNICODE_CHAT_VERSION,
+};
+use super::mining::{MiningJob, NonceMode, NonceSource};
+use chrono::Utc;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+pub const MAX_NICK_LEN: usize = 24;
+pub const MAX_CHANNEL_LEN: usize = 24;
+pub const MAX_REACTION_LEN: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MessageError {
+    Empty,
+    TooLong { len: usize, max: usize },
+    InvalidCharacter { position: usize, character: char },
+}
+
+impl fmt::Display for MessageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MessageError::Empty => write!(f, "mensagem vazia"),
+            MessageError::TooLong { len, max } => {
+                write!(f, "mensagem com {len} bytes excede o limite de {max}")
+            }
+            MessageError::InvalidCharacter {
+                position,
+                character,
+            } => write!(f, "caractere inválido {character:?} na posição {position}"),
+        }
+    }
+}
+
+fn is_hidden_format(c: char) -> bool {
+    matches!(
+        c,
+        '\u{200B}'..='\u{200D}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}' | '\u{FEFF}'
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchPolicy {
+    Stop,
+    Skip,
+}
+
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    pub mined: usize,
+    pub rejected: Vec<(usize, MessageError)>,
+    pub stopped: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NickError {
+    InvalidFormat,
+    LooksLikeFingerprint,
+    Taken { owner: [u8; 32] },
+}
+
+impl fmt::Display for NickError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NickError::InvalidFormat => write!(
+                f,
+                "apelido deve ter de 1 a {MAX_NICK_LEN} caracteres entre letras, dígitos, '_' e '-'"
+            ),
+            NickError::LooksLikeFingerprint => write!(
+                f,
+                "apelido não pode ter 8 dígitos hexadecimais, como a impressão digital de uma chave"
+            ),
+            NickError::Taken { owner } => {
+                write!(f, "apelido já registrado pela chave {}", fingerprint(owner))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    BadPrefix {
+        index: usize,
+        required_bits: u32,
+        actual: Vec<u8>,
+    },
+    HashMismatch {
+        index: usize,
+        expected: Vec<u8>,
+        actual: Vec<u8>,
+    },
+    InvalidMessage {
+        index: usize,
+        error: MessageError,
+    },
+    TimestampBeforePrevious {
+        index: usize,
+        timestamp: u64,
+        previous: u64,
+    },
+    TimestampInFuture {
+        index: usize,
+        timestamp: u64,
+        now: u64,
+    },
+    InvalidSignature {
+        index: usize,
+    },
+    InvalidNick {
+        index: usize,
+        error: NickError,
+    },
+    UnsignedRecord {
+        index: usize,
+    },
+    InvalidChannel {
+        index: usize,
+        channel: String,
+    },
+    InvalidReference {
+        index: usize,
+        target: u32,
+    },
+    InvalidRetraction {
+        index: usize,
+        target: u32,
+    },
+    InvalidGenesis {
+        index: usize,
+    },
+    CheckpointMismatch {
+        index: usize,
+        expected: Vec<u8>,
+        actual: Vec<u8>,
+    },
+    CheckpointNotReached {
+        index: usize,
+    },
+    MissingRecord {
+        index: usize,
+    },
+}
+
+impl ValidationError {
+    pub fn index(&self) -> usize {
+        match *self {
+            ValidationError::BadPrefix { index, .. }
+            | ValidationError::HashMismatch { index, .. }
+            | ValidationError::InvalidMessage { index, .. }
+            | ValidationError::TimestampBeforePrevious { index, .. }
+            | ValidationError::TimestampInFuture { index, .. }
+            | ValidationError::InvalidSignature { index }
+            | ValidationError::InvalidNick { index, .. }
+            | ValidationError::UnsignedRecord { index }
+            | ValidationError::InvalidChannel { index, .. }
+            | ValidationError::InvalidReference { index, .. }
+            | ValidationError::InvalidRetraction { index, .. }
+            | ValidationError::InvalidGenesis { index }
+            | ValidationError::CheckpointMismatch { index, .. }
+            | ValidationError::CheckpointNotReached { index }
+            | ValidationError::MissingRecord { index } => index,
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::BadPrefix {
+                index,
+                required_bits,
+                actual,
+            } => write!(
+                f,
+                "índice {index}: hash {} não começa com {required_bits} bits zero",
+                to_hex(actual)
+            ),
+            ValidationError::HashMismatch {
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "índice {index}: hash esperado {}, obtido {}",
+                to_hex(expected),
+                to_hex(actual)
+            ),
+            ValidationError::InvalidMessage { index, error } => {
+                write!(f, "índice {index}: {error}")
+            }
+            ValidationError::TimestampBeforePrevious {
+                index,
+                timestamp,
+                previous,
+            } => write!(
+                f,
+                "índice {index}: horário {timestamp} anterior ao mais recente já registrado ({previous})"
+            ),
+            ValidationError::TimestampInFuture {
+                index,
+                timestamp,
+                now,
+            } => write!(
+                f,
+                "índice {index}: horário {timestamp} no futuro (agora: {now})"
+            ),
+            ValidationError::InvalidSignature { index } => {
+                write!(f, "índice {index}: assinatura do autor inválida")
+            }
+            ValidationError::InvalidNick { index, error } => {
+                write!(f, "índice {index}: {error}")
+            }
+            ValidationError::UnsignedRecord { index } => {
+                write!(f, "índice {index}: registro sem autor")
+            }
+            ValidationError::InvalidChannel { index, channel } => {
+                write!(f, "índice {index}: nome de canal inválido '{channel}'")
+            }
+            ValidationError::InvalidReference { index, target } => write!(
+                f,
+                "índice {index}: referência à mensagem {target}, inexistente ou não anterior"
+            ),
+            ValidationError::InvalidRetraction { index, target } => write!(
+                f,
+                "índice {index}: retração inválida da mensagem {target} (inexistente, de outro autor ou já retirada)"
+            ),
+            ValidationError::InvalidGenesis { index: 0 } => write!(
+                f,
+                "índice 0: registro gênese ausente ou de outra rede (parâmetros diferentes)"
+            ),
+            ValidationError::InvalidGenesis { index } => {
+                write!(f, "índice {index}: registro gênese fora do índice 0")
+            }
+            ValidationError::CheckpointMismatch {
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "índice {index}: hash {} diverge do checkpoint {}",
+                to_hex(actual),
+                to_hex(expected)
+            ),
+            ValidationError::CheckpointNotReached { index } => {
+                write!(
+                    f,
+                    "o histórico termina antes do checkpoint no índice {index}"
+                )
+            }
+            ValidationError::MissingRecord { index } => {
+                write!(f, "índice {index}: não há registro nesta posição")
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ChainState {
+    config: ChainConfig,
+    len: usize,
+    difficulty_bits: u32,
+    window_start: u64,
+    retracted: HashSet<usize>,
+    nick_owners: HashMap<String, [u8; 32]>,
+    nicks: HashMap<[u8; 32], String>,
+    latest_timestamp: Option<u64>,
+}
+
+impl ChainState {
+    fn new(config: &ChainConfig) -> Self {
+        ChainState {
+            config: *config,
+            len: 0,
+            difficulty_bits: config.difficulty_bits,
+            window_start: 0,
+            retracted: HashSet::new(),
+            nick_owners: HashMap::new(),
+            nicks: HashMap::new(),
+            latest_timestamp: None,
+        }
+    }
+
+    fn of(chats: &[Chat], config: &ChainConfig) -> Self {
+        let mut state = ChainState::new(config);
+        for chat in chats {
+            state.record(chat);
+        }
+        state
+    }
+
+    fn record(&mut self, chat: &Chat) {
+        if let ChatKind::Retraction(reference) = &chat.kind {
+            self.retracted.insert(reference.index as usize);
+        }
+
+        if chat.kind == ChatKind::Nick
+            && let Some(author) = &chat.author
+        {
+            let nick = chat.message.to_ascii_lowercase();
+            if let Some(previous) = self.nicks.insert(author.public_key, nick.clone()) {
+                self.nick_owners.remove(&previous);
+            }
+            self.nick_owners.insert(nick, author.public_key);
+        }
+
+        self.latest_timestamp = self.latest_timestamp.max(chat.timestamp);
+
+        let (index, window) = (self.len, self.config.retarget_window as usize);
+        self.len += 1;
+        if window > 0 && index > 0 {
+            let latest = self.latest_timestamp.unwrap_or(0);
+            let position = (index - 1) % window;
+            if position == 0 {
+                self.window_start = latest;
+            }
+            if position == window - 1 {
+                let timespan = latest.saturating_sub(self.window_start);
+                self.difficulty_bits = retarget(self.difficulty_bits, timespan, &self.config);
+            }
+        }
+    }
+
+    fn nick_owner(&self, nick: &str) -> Option<[u8; 32]> {
+        self.nick_owners.get(&nick.to_ascii_lowercase()).copied()
+    }
+}
+
+pub struct MiningTemplate {
+    index: usize,
+    tip: Option<Vec<u8>>,
+    window: Vec<u8>,
+    difficulty_bits: u32,
+    hash_function: HashFunction,
+}
+
+impl MiningTemplate {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn solve(&self, mut chat: Chat, nonce: &mut dyn NonceSource) -> Chat {
+        logger::info(&format!(
+            "Minerando código de verificação para o registro '{}': '{}'...",
+            chat.kind.name(),
+            chat.message
+        ));
+
+        let hasher = self.hash_function.hasher();
+        let mut job = MiningJob::start(self.difficulty_bits);
+
+        loop {
+            nonce.fill(&mut chat.verification_code);
+            job.attempt();
+
+            let mut data_to_hash = self.window.clone();
+            data_to_hash.extend_from_slice(&chat.hashed_bytes());
+
+            let calculated_hash = hasher.digest(&data_to_hash);
+
+            if meets_difficulty(&calculated_hash, self.difficulty_bits) {
+                logger::info(&format!(
+                    "Código de verificação minerado: {}",
+                    to_hex(&chat.verification_code)
+                ));
+
+                logger::info(&format!(
+                    "Hash {} da mensagem: {}",
+                    self.hash_function.name(),
+                    to_hex(&calculated_hash)
+                ));
+
+                chat.hash = calculated_hash;
+                return chat;
+            }
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Archive {
     pub chats: Vec<Chat>,
+    pub config: ChainConfig,
+    pub nonce_mode: NonceMode,
+    pub fixed_timestamp: Option<u64>,
+    pub checkpoints: Checkpoints,
 }
 
 impl Archive {
-    pub fn new() -> Self {
-        Archive { chats: Vec::new() }
+    pub fn new(config: ChainConfig) -> Self {
+        Self::with_chats(vec![Self::genesis(&config)], config)
+    }
+
+    pub fn genesis(config: &ChainConfig) -> Chat {
+        let mut chat = Chat {
+            version: GENESIS_CHAT_VERSION,
+            kind: ChatKind::Genesis,
+            message: config.genesis_text(),
+            channel: Some(DEFAULT_CHANNEL.to_string()),
+            timestamp: Some(0),
+            author: Some(Author {
+                public_key: [0u8; 32],
+                signature: [0u8; 64],
+            }),
+            verification_code: [0u8; 16],
+            hash: Vec::new(),
+        };
+        chat.hash = config.hash_function.hasher().digest(&chat.hashed_bytes());
+        chat
+    }
+
+    pub fn genesis_id(config: &ChainConfig) -> [u8; 32] {
+        Self::genesis(config).content_hash()
+    }
+
+    pub fn with_chats(chats: Vec<Chat>, config: ChainConfig) -> Self {
+        Archive {
+            chats,
+            config,
+            nonce_mode: NonceMode::default(),
+            fixed_timestamp: None,
+            checkpoints: Checkpoints::new(),
+        }
     }
 
     pub fn to_bytes(&self) -> Vec<u8> {
@@ -27,146 +448,877 @@ impl Archive {
         bytes
     }
 
-    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+    pub fn from_bytes(data: &[u8], config: ChainConfig) -> Option<Self> {
         if data.len() < 5 || data[0] != MessageType::ArchiveResponse as u8 {
             return None;
         }
 
         let count = u32::from_be_bytes([data[1], data[2], data[3], data[4]]) as usize;
-        let mut chats = Vec::with_capacity(count);
+        let hash_len = config.hash_function.digest_len();
+        let min_record_len = 1 + 16 + hash_len;
+        let mut chats = Vec::with_capacity(count.min((data.len() - 5) / min_record_len));
         let mut offset = 5;
 
         for _ in 0..count {
-            if let Some((chat, size)) = Chat::from_bytes(&data[offset..]) {
+            if let Some((chat, size)) = Chat::from_bytes(&data[offset..], hash_len) {
                 chats.push(chat);
                 offset += size;
             } else {
                 return None;
             }
         }
-        Some(Archive { chats })
+        Some(Archive::with_chats(chats, config))
     }
 
-    pub fn is_valid(&self) -> bool {
+    pub fn validate(&self) -> Result<(), ValidationError> {
         if self.chats.is_empty() {
-            return true;
+            return Err(ValidationError::InvalidGenesis { index: 0 });
         }
 
-        for i in 0..self.chats.len() {
-            if !self.validate_chat_at_index(i) {
-                return false;
+        self.validate_checkpoint_reach()?;
+        let mut state = ChainState::new(&self.config);
+        for (index, chat) in self.chats.iter().enumerate() {
+            self.validate_chat(index, state.difficulty_bits, &state)?;
+            state.record(chat);
+        }
+
+        Ok(())
+    }
+
+    pub fn validate_checkpoint_reach(&self) -> Result<(), ValidationError> {
+        match self.checkpoints.last_index() {
+            Some(index) if index >= self.chats.len() => {
+                Err(ValidationError::CheckpointNotReached { index })
             }
+            _ => Ok(()),
         }
+    }
 
-        true
+    fn validate_chat(
+        &self,
+        index: usize,
+        required_bits: u32,
+        state: &ChainState,
+    ) -> Result<(), ValidationError> {
+        let Some(chat) = self.chats.get(index) else {
+            return Err(ValidationError::MissingRecord { index });
+        };
+        if let Some(expected) = self.checkpoints.get(index)
+            && chat.hash != expected
+        {
+            return Err(ValidationError::CheckpointMismatch {
+                index,
+                expected: expected.to_vec(),
+                actual: chat.hash.clone(),
+            });
+        }
+
+        if index == 0 {
+            return if chat.to_bytes() == Self::genesis(&self.config).to_bytes() {
+                Ok(())
+            } else {
+                Err(ValidationError::InvalidGenesis { index })
+            };
+        }
+
+        self.validate_record(index, chat, state)?;
+
+        if !meets_difficulty(&chat.hash, required_bits) {
+            return Err(ValidationError::BadPrefix {
+                index,
+                required_bits,
+                actual: chat.hash.clone(),
+            });
+        }
+
+        let mut data_to_hash = self.hash_window(index);
+        data_to_hash.extend_from_slice(&chat.hashed_bytes());
+
+        let calculated_hash = self.config.hash_function.hasher().digest(&data_to_hash);
+        if calculated_hash != chat.hash {
+            return Err(ValidationError::HashMismatch {
+                index,
+                expected: calculated_hash,
+                actual: chat.hash.clone(),
+            });
+        }
+
+        Ok(())
     }
 
-    fn validate_chat_at_index(&self, index: usize) -> bool {
-        if index >= self.chats.len() {
-            return false;
+    pub fn validate_pending(&self, chat: &Chat) -> Result<(), ValidationError> {
+        if chat.author.is_none() {
+            return Err(ValidationError::UnsignedRecord { index: self.len() });
         }
 
-        let chat = &self.chats[index];
-        if chat.md5_hash[0] != 0 || chat.md5_hash[1] != 0 {
-            return false;
+        self.validate_record(self.len(), chat, &ChainState::of(&self.chats, &self.config))
+    }
+
+    fn validate_record(
+        &self,
+        index: usize,
+        chat: &Chat,
+        state: &ChainState,
+    ) -> Result<(), ValidationError> {
+        match &chat.kind {
+            ChatKind::Genesis => return Err(ValidationError::InvalidGenesis { index }),
+            ChatKind::Direct(_) | ChatKind::Attachment(_) | ChatKind::Retraction(_)
+                if chat.author.is_none() =>
+            {
+                return Err(ValidationError::UnsignedRecord { index });
+            }
+            ChatKind::Attachment(manifest) => Self::validate_attachment_name(&manifest.name)
+                .map_err(|error| ValidationError::InvalidMessage { index, error })?,
+            ChatKind::Direct(_) | ChatKind::Retraction(_) => {}
+            ChatKind::Reaction(_) => Self::validate_reaction(&chat.message)
+                .map_err(|error| ValidationError::InvalidMessage { index, error })?,
+            _ => self
+                .validate_message(&chat.message, chat.version)
+                .map_err(|error| ValidationError::InvalidMessage { index, error })?,
         }
 
-        let start_index = index.saturating_sub(19);
-        let mut data_to_hash = Vec::new();
+        let (len, max) = (chat.body().len(), self.max_message_bytes(chat.version));
+        if len > max {
+            return Err(ValidationError::InvalidMessage {
+                index,
+                error: MessageError::TooLong { len, max },
+            });
+        }
+
+        if let Some(reference) = chat.kind.reference()
+            && !self.is_valid_reference(
+                index,
+                &chat.kind,
+                chat.author.as_ref().map(|a| &a.public_key),
+                state,
+            )
+        {
+            let target = reference.index;
+            return Err(match chat.kind {
+                ChatKind::Retraction(_) => ValidationError::InvalidRetraction { index, target },
+                _ => ValidationError::InvalidReference { index, target },
+            });
+        }
+
+        if let Some(channel) = &chat.channel
+            && !Self::is_valid_channel_name(channel)
+        {
+            return Err(ValidationError::InvalidChannel {
+                index,
+                channel: channel.clone(),
+            });
+        }
+
+        if chat.kind == ChatKind::Nick
+            && let Some(author) = &chat.author
+        {
+            Self::check_nick(&chat.message, &author.public_key, state)
+                .map_err(|error| ValidationError::InvalidNick { index, error })?;
+        }
+
+        if let Some(timestamp) = chat.timestamp {
+            self.validate_timestamp(index, timestamp, state.latest_timestamp)?;
+        }
+
+        if let Some(author) = &chat.author
+            && !verify_signature(&author.public_key, &chat.signed_bytes(), &author.signature)
+        {
+            return Err(ValidationError::InvalidSignature { index });
+        }
+
+        Ok(())
+    }
+
+    fn validate_timestamp(
+        &self,
+        index: usize,
+        timestamp: u64,
+        latest: Option<u64>,
+    ) -> Result<(), ValidationError> {
+        if let Some(previous) = latest
+            && timestamp < previous
+        {
+            return Err(ValidationError::TimestampBeforePrevious {
+                index,
+                timestamp,
+                previous,
+            });
+        }
+
+        let now = Utc::now().timestamp() as u64;
+        if timestamp > now.saturating_add(self.config.max_clock_skew_secs) {
+            return Err(ValidationError::TimestampInFuture {
+                index,
+                timestamp,
+                now,
+            });
+        }
+
+        Ok(())
+    }
 
-        for j in start_index..=index {
-            let chat = &self.chats[j];
+    fn latest_timestamp(&self) -> Option<u64> {
+        self.chats.iter().filter_map(|chat| chat.timestamp).max()
+    }
+
+    pub fn hash_window(&self, index: usize) -> Vec<u8> {
+        self.chats[index.saturating_sub(19)..index]
+            .iter()
+            .flat_map(Chat::to_bytes)
+            .collect()
+    }
+
+    pub fn required_difficulty(&self, index: usize) -> u32 {
+        ChainState::of(&self.chats[..index], &self.config).difficulty_bits
+    }
 
-            if !Self::is_valid_message(&chat.message) {
-                return false;
+    pub fn add_message(&mut self, message: String, channel: &str, identity: &Identity) -> bool {
+        match self.prepare_message(message, channel, identity) {
+            Some(chat) => {
+                self.mine(chat);
+                true
             }
+            None => false,
+        }
+    }
 
-            let chat_bytes = chat.to_bytes();
+    pub fn prepare_message(
+        &self,
+        message: String,
+        channel: &str,
+        identity: &Identity,
+    ) -> Option<Chat> {
+        if let Err(e) = self.validate_message(&message, CURRENT_CHAT_VERSION) {
+            println!(
+                "Erro: Mensagem inválida ({e}). Deve conter entre 1 e {} bytes de texto sem caracteres de controle ou invisíveis.",
+                self.config.max_message_bytes
+            );
+            return None;
+        }
 
-            if j == index {
-                data_to_hash.extend_from_slice(&chat_bytes[..chat_bytes.len() - 16]);
+        if !Self::is_valid_channel_name(channel) {
+            println!("Erro: nome de canal inválido '{channel}'.");
+            return None;
+        }
+
+        Some(self.sign_record(ChatKind::Text, channel, message, identity))
+    }
+
+    pub fn add_messages<F>(
+        &mut self,
+        messages: &[String],
+        channel: &str,
+        identity: &Identity,
+        policy: BatchPolicy,
+        mut progress: F,
+    ) -> BatchReport
+    where
+        F: FnMut(usize, usize),
+    {
+        let mut report = BatchReport::default();
+        if !Self::is_valid_channel_name(channel) {
+            println!("Erro: nome de canal inválido '{channel}'.");
+            report.stopped = true;
+            return report;
+        }
+
+        for (position, message) in messages.iter().enumerate() {
+            if let Err(e) = self.validate_message(message, CURRENT_CHAT_VERSION) {
+                report.rejected.push((position, e));
+                if policy == BatchPolicy::Stop {
+                    report.stopped = true;
+                    break;
+                }
             } else {
-                data_to_hash.extend_from_slice(&chat_bytes);
+                let chat = self.sign_record(ChatKind::Text, channel, message.clone(), identity);
+                self.mine(chat);
+                report.mined += 1;
             }
+            progress(position + 1, messages.len());
+        }
+
+        report
+    }
+
+    pub fn register_nick(&mut self, nick: String, identity: &Identity) -> bool {
+        let state = ChainState::of(&self.chats, &self.config);
+        if let Err(e) = Self::check_nick(&nick, &identity.public_key(), &state) {
+            println!("Erro: {e}.");
+            return false;
+        }
+
+        self.mine_record(ChatKind::Nick, DEFAULT_CHANNEL, nick, identity);
+        true
+    }
+
+    pub fn send_direct(&mut self, recipient: &[u8; 32], text: &str, identity: &Identity) -> bool {
+        if let Err(e) = self.validate_message(text, CURRENT_CHAT_VERSION) {
+            println!("Erro: Mensagem inválida ({e}).");
+            return false;
         }
 
-        let calculated_hash = md5::compute(&data_to_hash);
+        let max_text_len = self.config.max_message_bytes - DIRECT_OVERHEAD;
+        if text.len() > max_text_len {
+            println!("Erro: Mensagens diretas podem ter no máximo {max_text_len} bytes.");
+            return false;
+        }
 
-        calculated_hash.0 == chat.md5_hash
+        match direct::encrypt(identity, recipient, text) {
+            Some(direct) => {
+                self.mine_record(
+                    ChatKind::Direct(direct),
+                    DEFAULT_CHANNEL,
+                    String::new(),
+                    identity,
+                );
+                true
+            }
+            None => {
+                println!("Erro: chave do destinatário inválida.");
+                false
+            }
+        }
     }
 
-    pub fn add_message(&mut self, message: String) -> bool {
-        if !Self::is_valid_message(&message) {
+    pub fn add_attachment(
+        &mut self,
+        manifest: AttachmentManifest,
+        channel: &str,
+        identity: &Identity,
+    ) -> bool {
+        if let Err(e) = Self::validate_attachment_name(&manifest.name) {
+            println!("Erro: nome de arquivo inválido ({e}).");
+            return false;
+        }
+
+        let (len, max) = (
+            manifest.to_bytes().len(),
+            self.max_message_bytes(CURRENT_CHAT_VERSION),
+        );
+        if len > max {
             println!(
-                "Erro: Mensagem inválida. Deve conter entre 1 e 255 caracteres ASCII (32-126)."
+                "Erro: o manifesto do anexo ocupa {len} bytes e excede o limite de {max}; envie um arquivo menor."
             );
             return false;
         }
 
-        logger::info(&format!(
-            "Minerando código de verificação para a mensagem: '{message}'..."
-        ));
+        if !Self::is_valid_channel_name(channel) {
+            println!("Erro: nome de canal inválido '{channel}'.");
+            return false;
+        }
 
-        let mut rng = rand::rng();
+        self.mine_record(
+            ChatKind::Attachment(manifest),
+            channel,
+            String::new(),
+            identity,
+        );
+        true
+    }
 
-        loop {
-            let mut verification_code = [0u8; 16];
-            rng.fill(&mut verification_code);
+    pub fn add_reply(&mut self, target: usize, text: String, identity: &Identity) -> bool {
+        let Some(kind) = self.referencing_kind(target, ChatKind::Reply, identity, "respondida")
+        else {
+            return false;
+        };
 
-            let temp_chat = Chat {
-                message: message.clone(),
-                verification_code,
-                md5_hash: [0u8; 16],
-            };
+        if let Err(e) = self.validate_message(&text, CURRENT_CHAT_VERSION) {
+            println!("Erro: Mensagem inválida ({e}).");
+            return false;
+        }
 
-            let start_index = self.chats.len().saturating_sub(19);
-            let mut data_to_hash = Vec::new();
+        let channel = self.chats[target].channel().to_string();
+        self.mine_record(kind, &channel, text, identity);
+        true
+    }
+
+    pub fn add_reaction(&mut self, target: usize, reaction: String, identity: &Identity) -> bool {
+        let Some(kind) = self.referencing_kind(target, ChatKind::Reaction, identity, "reagida")
+        else {
+            return false;
+        };
+
+        if let Err(e) = Self::validate_reaction(&reaction) {
+            println!("Erro: Reação inválida ({e}).");
+            return false;
+        }
+
+        let channel = self.chats[target].channel().to_string();
+        self.mine_record(kind, &channel, reaction, identity);
+        true
+    }
 
-            for i in start_index..self.chats.len() {
-                data_to_hash.extend_from_slice(&self.chats[i].to_bytes());
+    pub fn retract(&mut self, target: usize, identity: &Identity) -> bool {
+        let Some(kind) =
+            self.referencing_kind(target, ChatKind::Retraction, identity, "retirada por você")
+        else {
+            return false;
+        };
+
+        let channel = self.chats[target].channel().to_string();
+        self.mine_record(kind, &channel, String::new(), identity);
+        true
+    }
+
+    fn referencing_kind(
+        &self,
+        target: usize,
+        make_kind: fn(ChatReference) -> ChatKind,
+        identity: &Identity,
+        action: &str,
+    ) -> Option<ChatKind> {
+        let state = ChainState::of(&self.chats, &self.config);
+        let kind = self
+            .chats
+            .get(target)
+            .map(|chat| make_kind(ChatReference::to(target, chat)))
+            .filter(|kind| {
+                self.is_valid_reference(self.len(), kind, Some(&identity.public_key()), &state)
+            });
+        if kind.is_none() {
+            println!("Erro: não há mensagem no índice {target} que possa ser {action}.");
+        }
+        kind
+    }
+
+    fn is_valid_reference(
+        &self,
+        index: usize,
+        kind: &ChatKind,
+        author: Option<&[u8; 32]>,
+        state: &ChainState,
+    ) -> bool {
+        let Some(reference) = kind.reference() else {
+            return true;
+        };
+
+        let position = reference.index as usize;
+        let Some(target) = self.chats[..index]
+            .get(position)
+            .filter(|chat| reference.matches(chat))
+        else {
+            return false;
+        };
+
+        match kind {
+            ChatKind::Retraction(_) => {
+                author.is_some()
+                    && target.author.as_ref().map(|a| &a.public_key) == author
+                    && !matches!(
+                        target.kind,
+                        ChatKind::Nick | ChatKind::Retraction(_) | ChatKind::Genesis
+                    )
+                    && !state.retracted.contains(&position)
             }
+            _ => !matches!(
+                target.kind,
+                ChatKind::Reaction(_) | ChatKind::Retraction(_) | ChatKind::Genesis
+            ),
+        }
+    }
 
-            let temp_bytes = temp_chat.to_bytes();
-            data_to_hash.extend_from_slice(&temp_bytes[..temp_bytes.len() - 16]);
+    pub fn retracted(&self) -> HashSet<usize> {
+        self.chats
+            .iter()
+            .filter_map(|chat| match &chat.kind {
+                ChatKind::Retraction(reference) => Some(reference.index as usize),
+                _ => None,
+            })
+            .collect()
+    }
 
-            let calculated_hash = md5::compute(&data_to_hash).0;
+    pub fn find_attachment(&self, id: &str) -> Option<(usize, &AttachmentManifest)> {
+        let id = id.to_ascii_lowercase();
+        let mut attachments = self
+            .chats
+            .iter()
+            .enumerate()
+            .filter_map(|(index, chat)| match &chat.kind {
+                ChatKind::Attachment(manifest) => Some((index, manifest)),
+                _ => None,
+            });
 
-            if calculated_hash[0] == 0 && calculated_hash[1] == 0 {
-                let final_chat = Chat {
-                    message,
-                    verification_code,
-                    md5_hash: calculated_hash,
-                };
+        attachments
+            .clone()
+            .find(|(index, _)| index.to_string() == id)
+            .or_else(|| {
+                attachments.find(|(_, manifest)| to_hex(&manifest.file_hash).starts_with(&id))
+            })
+    }
 
-                self.chats.push(final_chat);
+    pub fn resolve_key(&self, name: &str) -> Option<[u8; 32]> {
+        if let Some(key) = from_hex(name).and_then(|bytes| bytes.try_into().ok()) {
+            return Some(key);
+        }
 
-                let verification_code_str = verification_code
-                    .iter()
-                    .map(|b| format!("{b:02x}"))
-                    .collect::<String>();
+        let authors: Vec<[u8; 32]> = self
+            .chats
+            .iter()
+            .filter_map(|chat| chat.author.as_ref().map(|author| author.public_key))
+            .collect();
 
-                let md5_hash_str = calculated_hash
-                    .iter()
-                    .map(|b| format!("{b:02x}"))
-                    .collect::<String>();
+        ChainState::of(&self.chats, &self.config)
+            .nick_owner(name)
+            .or_else(|| {
+                authors
+                    .into_iter()
+                    .find(|key| fingerprint(key).eq_ignore_ascii_case(name))
+            })
+    }
 
-                logger::info(&format!(
-                    "Código de verificação minerado: {verification_code_str}"
-                ));
+    fn mine_record(&mut self, kind: ChatKind, channel: &str, message: String, identity: &Identity) {
+        let chat = self.sign_record(kind, channel, message, identity);
+        self.mine(chat);
+    }
+
+    fn sign_record(
+        &self,
+        kind: ChatKind,
+        channel: &str,
+        message: String,
+        identity: &Identity,
+    ) -> Chat {
+        let mut chat = Chat {
+            version: CURRENT_CHAT_VERSION,
+            kind,
+            message,
+            channel: Some(channel.to_string()),
+            timestamp: Some(
+                self.fixed_timestamp
+                    .unwrap_or_else(|| Utc::now().timestamp() as u64)
+                    .max(self.latest_timestamp().unwrap_or(0)),
+            ),
+            author: Some(Author {
+                public_key: identity.public_key(),
+                signature: [0u8; 64],
+            }),
+            verification_code: [0u8; 16],
+            hash: Vec::new(),
+        };
+
+        let signature = identity.sign(&chat.signed_bytes());
+        if let Some(author) = chat.author.as_mut() {
+            author.signature = signature;
+        }
+        chat
+    }
+
+    pub fn mine(&mut self, chat: Chat) {
+        let mut nonce = self.nonce_mode.source(self.len());
+        self.mine_with(chat, nonce.as_mut());
+    }
+
+    pub fn mine_with(&mut self, chat: Chat, nonce: &mut dyn NonceSource) {
+        let template = self.mining_template();
+        let chat = template.solve(chat, nonce);
+        self.push_mined(&template, chat);
+    }
+
+    pub fn mining_template(&self) -> MiningTemplate {
+        MiningTemplate {
+            index: self.len(),
+            tip: self.chats.last().map(|chat| chat.hash.clone()),
+            window: self.hash_window(self.len()),
+            difficulty_bits: self.required_difficulty(self.len()),
+            hash_function: self.config.hash_function,
+        }
+    }
+
+    pub fn push_mined(&mut self, template: &MiningTemplate, chat: Chat) -> bool {
+        if self.len() != template.index
+            || self.chats.last().map(|chat| &chat.hash) != template.tip.as_ref()
+        {
+            return false;
+        }
+
+        self.chats.push(chat);
+        self.update_checkpoints();
+        true
+    }
 
-                logger::info(&format!("Hash MD5 da mensagem: {md5_hash_str}"));
-                return true;
+    pub fn update_checkpoints(&mut self) {
+        let confirmations = self.config.checkpoint_confirmations as usize;
+        if confirmations > 0 {
+            self.checkpoints.confirm(&self.chats, confirmations);
+        }
+    }
+
+    pub fn contains_content(&self, content_hash: &[u8; 32]) -> bool {
+        self.chats
+            .iter()
+            .any(|chat| chat.content_hash() == *content_hash)
+    }
+
+    pub fn content_hashes(&self) -> HashSet<[u8; 32]> {
+        self.chats.iter().map(Chat::content_hash).collect()
+    }
+
+    pub fn is_valid_channel_name(channel: &str) -> bool {
+        !channel.is_empty()
+            && channel.len() <= MAX_CHANNEL_LEN
+            && channel
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+    }
+
+    pub fn channels(&self) -> BTreeMap<String, usize> {
+        let mut channels = BTreeMap::new();
+        for chat in self.chats.iter().filter(|chat| chat.kind == ChatKind::Text) {
+            *channels.entry(chat.channel().to_string()).or_insert(0) += 1;
+        }
+        channels
+    }
+
+    fn check_nick(nick: &str, owner: &[u8; 32], state: &ChainState) -> Result<(), NickError> {
+        let well_formed = !nick.is_empty()
+            && nick.len() <= MAX_NICK_LEN
+            && nick
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !well_formed {
+            return Err(NickError::InvalidFormat);
+        }
+        if nick.len() == 8 && nick.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(NickError::LooksLikeFingerprint);
+        }
+
+        match state.nick_owner(nick) {
+            Some(registered) if registered != *owner => Err(NickError::Taken { owner: registered }),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn nicknames(&self) -> HashMap<[u8; 32], String> {
+        self.chats
+            .iter()
+            .filter(|chat| chat.kind == ChatKind::Nick)
+            .filter_map(|chat| {
+                chat.author
+                    .as_ref()
+                    .map(|author| (author.public_key, chat.message.clone()))
+            })
+            .collect()
+    }
+
+    pub fn max_message_bytes(&self, version: u8) -> usize {
+        if version >= UNICODE_CHAT_VERSION {
+            self.config.max_message_bytes
+        } else {
+            LEGACY_MAX_MESSAGE_BYTES
+        }
+    }
+
+    pub fn validate_message(&self, message: &str, version: u8) -> Result<(), MessageError> {
+        if message.is_empty() {
+            return Err(MessageError::Empty);
+        }
+
+        let max = self.max_message_bytes(version);
+        if message.len() > max {
+            return Err(MessageError::TooLong {
+                len: message.len(),
+                max,
+            });
+        }
+
+        let unicode = version >= UNICODE_CHAT_VERSION;
+        match message.chars().enumerate().find(|(_, c)| {
+            if unicode {
+                c.is_control() || is_hidden_format(*c)
+            } else {
+                !(c.is_ascii_graphic() || *c == ' ')
             }
+        }) {
+            Some((position, character)) => Err(MessageError::InvalidCharacter {
+                position,
+                character,
+            }),
+            None => Ok(()),
         }
     }
 
-    fn is_valid_message(message: &str) -> bool {
-        !message.is_empty()
-            && message.len() <= 255
-            && message.chars().all(|c| c.is_ascii_graphic() || c == ' ')
+    pub fn validate_reaction(reaction: &str) -> Result<(), MessageError> {
+        if reaction.is_empty() {
+            return Err(MessageError::Empty);
+        }
+
+        if reaction.len() > MAX_REACTION_LEN {
+            return Err(MessageError::TooLong {
+                len: reaction.len(),
+                max: MAX_REACTION_LEN,
+            });
+        }
+
+        match reaction
+            .chars()
+            .enumerate()
+            .find(|(_, c)| c.is_control() || c.is_whitespace() || is_hidden_format(*c))
+        {
+            Some((position, character)) => Err(MessageError::InvalidCharacter {
+                position,
+                character,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    pub fn validate_attachment_name(name: &str) -> Result<(), MessageError> {
+        if name.is_empty() {
+            return Err(MessageError::Empty);
+        }
+
+        if name.len() > MAX_ATTACHMENT_NAME_LEN {
+            return Err(MessageError::TooLong {
+                len: name.len(),
+                max: MAX_ATTACHMENT_NAME_LEN,
+            });
+        }
+
+        match name
+            .chars()
+            .enumerate()
+            .find(|(_, c)| c.is_control() || is_hidden_format(*c))
+        {
+            Some((position, character)) => Err(MessageError::InvalidCharacter {
+                position,
+                character,
+            }),
+            None => Ok(()),
+        }
     }
 
     pub fn len(&self) -> usize {
         self.chats.len()
     }
+
+    pub fn cumulative_work(&self) -> u128 {
+        self.chats.iter().fold(0u128, |work, chat| {
+            work.saturating_add(work_for_hash(&chat.hash))
+        })
+    }
+
+    pub fn is_better_than(&self, other: &Archive) -> bool {
+        match self.cumulative_work().cmp(&other.cumulative_work()) {
+            Ordering::Greater => true,
+            Ordering::Less => false,
+            Ordering::Equal => match (self.chats.last(), other.chats.last()) {
+                (Some(ours), Some(theirs)) => ours.hash < theirs.hash,
+                _ => false,
+            },
+        }
+    }
+
+    pub fn common_prefix_len(&self, other: &Archive) -> usize {
+        self.chats
+            .iter()
+            .zip(other.chats.iter())
+            .take_while(|(a, b)| a.hash == b.hash)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::message::LEGACY_CHAT_VERSION;
+
+    #[test]
+    fn sequential_mining_with_fixed_clock_is_reproducible() {
+        let mut config = ChainConfig::default();
+        config.set_fixed_difficulty("8").unwrap();
+        let identity = Identity::from_seed(&[7u8; 32]);
+
+        let mut archive = Archive::new(config);
+        archive.nonce_mode = NonceMode::Sequential;
+        archive.fixed_timestamp = Some(1_700_000_000);
+        assert!(archive.add_message("olá".to_string(), DEFAULT_CHANNEL, &identity));
+        assert!(archive.register_nick("alice".to_string(), &identity));
+        assert!(archive.add_message("segunda".to_string(), "dev", &identity));
+
+        assert_eq!(
+            archive.to_bytes(),
+            include_bytes!("testdata/sequential_archive.raw")
+        );
+        assert_eq!(archive.validate(), Ok(()));
+    }
+
+    #[test]
+    fn timestamps_may_not_drift_backwards() {
+        let mut config = ChainConfig::default();
+        config.set_fixed_difficulty("8").unwrap();
+        let identity = Identity::from_seed(&[7u8; 32]);
+        let mut archive = Archive::new(config);
+        archive.nonce_mode = NonceMode::Sequential;
+
+        for step in 0..10 {
+            archive.fixed_timestamp = Some(1_700_000_000);
+            let mut chat = archive.sign_record(
+                ChatKind::Text,
+                DEFAULT_CHANNEL,
+                format!("{step}"),
+                &identity,
+            );
+            chat.timestamp = Some(1_700_000_000 - step * 299);
+            let signature = identity.sign(&chat.signed_bytes());
+            chat.author.as_mut().unwrap().signature = signature;
+            archive.mine(chat);
+        }
+
+        assert!(matches!(
+            archive.validate(),
+            Err(ValidationError::TimestampBeforePrevious { index: 2, .. })
+        ));
+    }
+
+    fn unmined_chat(timestamp: Option<u64>) -> Chat {
+        Chat {
+            version: if timestamp.is_some() {
+                CURRENT_CHAT_VERSION
+            } else {
+                LEGACY_CHAT_VERSION
+            },
+            kind: ChatKind::Text,
+            message: "x".to_string(),
+            channel: None,
+            timestamp,
+            author: None,
+            verification_code: [0u8; 16],
+            hash: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn difficulty_is_retargeted_only_at_window_boundaries() {
+        let config = ChainConfig::default();
+        let mut archive = Archive::new(config);
+        for step in 1..=10 {
+            archive.chats.push(unmined_chat(Some(1_000 + step * 10)));
+        }
+        for step in 1..=10 {
+            archive.chats.push(unmined_chat(Some(2_000 + step * 60)));
+        }
+
+        for index in 1..=10 {
+            assert_eq!(archive.required_difficulty(index), config.difficulty_bits);
+        }
+        for index in 11..=21 {
+            assert_eq!(
+                archive.required_difficulty(index),
+                config.difficulty_bits + 2
+            );
+        }
+    }
+
+    #[test]
+    fn legacy_records_keep_the_latest_timestamp_in_the_window() {
+        let config = ChainConfig::default();
+        let mut archive = Archive::new(config);
+        for step in 1..=9 {
+            archive.chats.push(unmined_chat(Some(1_000 + step * 300)));
+        }
+        archive.chats.push(unmined_chat(None));
+
+        assert_eq!(archive.required_difficulty(10), config.difficulty_bits);
+        assert_eq!(archive.required_difficulty(11), config.difficulty_bits - 2);
+    }
 }
@@ -0,0 +1,97 @@
+use super::hex::to_hex;
+use super::message::{AttachmentManifest, CHUNK_SIZE};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub fn content_hash(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+pub struct ChunkStore {
+    dir: PathBuf,
+}
+
+impl ChunkStore {
+    pub fn new(dir: &str) -> Self {
+        ChunkStore {
+            dir: PathBuf::from(dir),
+        }
+    }
+
+    fn path(&self, hash: &[u8; 32]) -> PathBuf {
+        self.dir.join(to_hex(hash))
+    }
+
+    pub fn contains(&self, hash: &[u8; 32]) -> bool {
+        self.path(hash).exists()
+    }
+
+    pub fn get(&self, hash: &[u8; 32]) -> Option<Vec<u8>> {
+        fs::read(self.path(hash))
+            .ok()
+            .filter(|data| content_hash(data) == *hash)
+    }
+
+    pub fn put(&self, data: &[u8]) -> io::Result<[u8; 32]> {
+        let hash = content_hash(data);
+        if !self.contains(&hash) {
+            fs::create_dir_all(&self.dir)?;
+            fs::write(self.path(&hash), data)?;
+        }
+        Ok(hash)
+    }
+
+    pub fn store_file(&self, path: &str) -> io::Result<AttachmentManifest> {
+        let data = fs::read(path)?;
+        let name = Path::new(path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("anexo")
+            .to_string();
+
+        let chunks = data
+            .chunks(CHUNK_SIZE)
+            .map(|chunk| self.put(chunk))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        Ok(AttachmentManifest {
+            file_hash: content_hash(&data),
+            size: data.len() as u64,
+            name,
+            chunks,
+        })
+    }
+
+    pub fn missing_chunks(&self, manifest: &AttachmentManifest) -> Vec<[u8; 32]> {
+        manifest
+            .chunks
+            .iter()
+            .filter(|hash| !self.contains(hash))
+            .copied()
+            .collect()
+    }
+
+    pub fn assemble(&self, manifest: &AttachmentManifest) -> io::Result<Vec<u8>> {
+        let mut data = Vec::with_capacity(manifest.size as usize);
+        for hash in &manifest.chunks {
+            let chunk = self.get(hash).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("pedaço {} ausente ou corrompido", to_hex(&hash[..4])),
+                )
+            })?;
+            data.extend_from_slice(&chunk);
+        }
+
+        if data.len() as u64 != manifest.size || content_hash(&data) != manifest.file_hash {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "o arquivo remontado não confere com o hash do manifesto",
+            ));
+        }
+
+        Ok(data)
+    }
+}
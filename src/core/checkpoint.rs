@@ -0,0 +1,139 @@
+use super::hex::from_hex;
+use super::message::Chat;
+use std::collections::BTreeMap;
+use std::fs;
+
+#[derive(Debug, Clone, Default)]
+pub struct Checkpoints {
+    hashes: BTreeMap<usize, Vec<u8>>,
+    confirmed: usize,
+}
+
+impl Checkpoints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Erro ao ler checkpoints '{path}': {e}"))?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut checkpoints = Checkpoints::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = || format!("Linha {} inválida: '{line}'", number + 1);
+            let (index, hash) = line.split_once(char::is_whitespace).ok_or_else(invalid)?;
+            let index = index.parse().map_err(|_| invalid())?;
+            let hash = from_hex(hash.trim())
+                .filter(|hash| !hash.is_empty())
+                .ok_or_else(invalid)?;
+
+            if checkpoints.hashes.insert(index, hash).is_some() {
+                return Err(format!("Checkpoint duplicado para o índice {index}"));
+            }
+        }
+
+        Ok(checkpoints)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&[u8]> {
+        self.hashes.get(&index).map(Vec::as_slice)
+    }
+
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    pub fn last_index(&self) -> Option<usize> {
+        self.hashes.keys().next_back().copied()
+    }
+
+    pub fn first_conflict(&self, chats: &[Chat]) -> Option<usize> {
+        self.hashes
+            .iter()
+            .find(|&(&index, hash)| chats.get(index).is_none_or(|chat| chat.hash != *hash))
+            .map(|(&index, _)| index)
+    }
+
+    pub fn confirm(&mut self, chats: &[Chat], confirmations: usize) {
+        let end = chats.len().saturating_sub(confirmations);
+        for (index, chat) in chats.iter().enumerate().take(end).skip(self.confirmed) {
+            self.hashes
+                .entry(index)
+                .or_insert_with(|| chat.hash.clone());
+        }
+        self.confirmed = self.confirmed.max(end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::message::{ChatKind, LEGACY_CHAT_VERSION};
+
+    fn chats(hashes: impl IntoIterator<Item = u8>) -> Vec<Chat> {
+        hashes
+            .into_iter()
+            .map(|hash| Chat {
+                version: LEGACY_CHAT_VERSION,
+                kind: ChatKind::Text,
+                message: "x".to_string(),
+                channel: None,
+                timestamp: None,
+                author: None,
+                verification_code: [0u8; 16],
+                hash: vec![hash],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn confirm_records_only_buried_chats() {
+        let mut checkpoints = Checkpoints::new();
+        checkpoints.confirm(&chats(0..3), 5);
+        assert!(checkpoints.is_empty());
+
+        checkpoints.confirm(&chats(0..10), 3);
+        assert_eq!(checkpoints.len(), 7);
+        assert_eq!(checkpoints.last_index(), Some(6));
+        assert_eq!(checkpoints.get(6), Some(&[6u8][..]));
+        assert_eq!(checkpoints.get(7), None);
+    }
+
+    #[test]
+    fn confirm_never_rewrites_an_existing_checkpoint() {
+        let mut checkpoints = Checkpoints::new();
+        checkpoints.confirm(&chats(0..10), 3);
+        checkpoints.confirm(&chats(100..112), 3);
+
+        assert_eq!(checkpoints.last_index(), Some(8));
+        assert_eq!(checkpoints.get(6), Some(&[6u8][..]));
+        assert_eq!(checkpoints.get(7), Some(&[107u8][..]));
+    }
+
+    #[test]
+    fn first_conflict_reports_mismatched_and_unreached_checkpoints() {
+        let checkpoints = Checkpoints::parse("2 02\n5 05\n").unwrap();
+
+        assert_eq!(checkpoints.first_conflict(&chats(0..8)), None);
+        assert_eq!(checkpoints.first_conflict(&chats(0..4)), Some(5));
+        assert_eq!(checkpoints.first_conflict(&chats(10..18)), Some(2));
+
+        let mut forked = chats(0..8);
+        forked[5].hash = vec![0xff];
+        assert_eq!(checkpoints.first_conflict(&forked), Some(5));
+        assert_eq!(Checkpoints::new().first_conflict(&[]), None);
+    }
+}
@@ -0,0 +1,170 @@
+use super::hasher::HashFunction;
+use super::message::MAX_BODY_LEN;
+use std::fs;
+
+pub const DEFAULT_DIFFICULTY_BITS: u32 = 16;
+pub const DEFAULT_MIN_DIFFICULTY_BITS: u32 = 8;
+pub const DEFAULT_MAX_DIFFICULTY_BITS: u32 = 32;
+pub const DEFAULT_RETARGET_WINDOW: u32 = 10;
+pub const DEFAULT_TARGET_INTERVAL_SECS: u64 = 60;
+pub const DEFAULT_MAX_CLOCK_SKEW_SECS: u64 = 300;
+pub const MAX_DIFFICULTY_BITS: u32 = 256;
+pub const DEFAULT_MAX_MESSAGE_BYTES: usize = 1024;
+pub const LEGACY_MAX_MESSAGE_BYTES: usize = 255;
+pub const DEFAULT_NETWORK_ID: u32 = 0;
+pub const DEFAULT_CHECKPOINT_CONFIRMATIONS: u32 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChainConfig {
+    pub network_id: u32,
+    pub difficulty_bits: u32,
+    pub min_difficulty_bits: u32,
+    pub max_difficulty_bits: u32,
+    pub retarget_window: u32,
+    pub target_interval_secs: u64,
+    pub max_clock_skew_secs: u64,
+    pub hash_function: HashFunction,
+    pub max_message_bytes: usize,
+    pub checkpoint_confirmations: u32,
+}
+
+impl Default for ChainConfig {
+    fn default() -> Self {
+        ChainConfig {
+            network_id: DEFAULT_NETWORK_ID,
+            difficulty_bits: DEFAULT_DIFFICULTY_BITS,
+            min_difficulty_bits: DEFAULT_MIN_DIFFICULTY_BITS,
+            max_difficulty_bits: DEFAULT_MAX_DIFFICULTY_BITS,
+            retarget_window: DEFAULT_RETARGET_WINDOW,
+            target_interval_secs: DEFAULT_TARGET_INTERVAL_SECS,
+            max_clock_skew_secs: DEFAULT_MAX_CLOCK_SKEW_SECS,
+            hash_function: HashFunction::Md5,
+            max_message_bytes: DEFAULT_MAX_MESSAGE_BYTES,
+            checkpoint_confirmations: DEFAULT_CHECKPOINT_CONFIRMATIONS,
+        }
+    }
+}
+
+impl ChainConfig {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Erro ao ler configuração '{path}': {e}"))?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut config = ChainConfig::default();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or(format!("Linha {} inválida: '{line}'", number + 1))?;
+
+            config.set(key.trim(), value.trim())?;
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "network_id" => self.network_id = parse_number(key, value)?,
+            "difficulty_bits" => self.difficulty_bits = parse_difficulty(value)?,
+            "min_difficulty_bits" => self.min_difficulty_bits = parse_difficulty(value)?,
+            "max_difficulty_bits" => self.max_difficulty_bits = parse_difficulty(value)?,
+            "retarget_window" => self.retarget_window = parse_number(key, value)?,
+            "target_interval_secs" => self.target_interval_secs = parse_number(key, value)?,
+            "max_clock_skew_secs" => self.max_clock_skew_secs = parse_number(key, value)?,
+            "max_message_bytes" => self.max_message_bytes = parse_number(key, value)?,
+            "checkpoint_confirmations" => self.checkpoint_confirmations = parse_number(key, value)?,
+            "hash_function" => {
+                self.hash_function = HashFunction::from_name(value)
+                    .ok_or(format!("Função de hash desconhecida: '{value}'"))?
+            }
+            _ => return Err(format!("Parâmetro de configuração desconhecido: '{key}'")),
+        }
+
+        Ok(())
+    }
+
+    pub fn genesis_text(&self) -> String {
+        format!(
+            "network_id={} difficulty_bits={} min_difficulty_bits={} max_difficulty_bits={} retarget_window={} target_interval_secs={} max_clock_skew_secs={} hash_function={} max_message_bytes={}",
+            self.network_id,
+            self.difficulty_bits,
+            self.min_difficulty_bits,
+            self.max_difficulty_bits,
+            self.retarget_window,
+            self.target_interval_secs,
+            self.max_clock_skew_secs,
+            self.hash_function.name().to_ascii_lowercase(),
+            self.max_message_bytes
+        )
+    }
+
+    pub fn set_fixed_difficulty(&mut self, value: &str) -> Result<(), String> {
+        let bits = parse_difficulty(value)?;
+        self.difficulty_bits = bits;
+        self.min_difficulty_bits = bits;
+        self.max_difficulty_bits = bits;
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let max_bits = self.hash_function.digest_len() as u32 * 8;
+        if self.max_difficulty_bits > max_bits {
+            return Err(format!(
+                "max_difficulty_bits ({}) excede o tamanho do hash {} ({max_bits} bits)",
+                self.max_difficulty_bits,
+                self.hash_function.name()
+            ));
+        }
+
+        if self.min_difficulty_bits > self.difficulty_bits
+            || self.difficulty_bits > self.max_difficulty_bits
+        {
+            return Err(format!(
+                "difficulty_bits ({}) fora do intervalo {}..={} definido por min/max_difficulty_bits",
+                self.difficulty_bits, self.min_difficulty_bits, self.max_difficulty_bits
+            ));
+        }
+
+        if !(LEGACY_MAX_MESSAGE_BYTES..=MAX_BODY_LEN).contains(&self.max_message_bytes) {
+            return Err(format!(
+                "max_message_bytes ({}) fora do intervalo {LEGACY_MAX_MESSAGE_BYTES}..={MAX_BODY_LEN}",
+                self.max_message_bytes
+            ));
+        }
+
+        if self.retarget_window == 1 {
+            return Err("retarget_window deve ser 0 (desativado) ou pelo menos 2".to_string());
+        }
+
+        if self.retarget_window > 0 && self.target_interval_secs == 0 {
+            return Err("target_interval_secs deve ser maior que zero".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_difficulty(value: &str) -> Result<u32, String> {
+    match value.parse::<u32>() {
+        Ok(bits) if bits <= MAX_DIFFICULTY_BITS => Ok(bits),
+        _ => Err(format!(
+            "Dificuldade inválida: '{value}' (esperado 0 a {MAX_DIFFICULTY_BITS} bits)"
+        )),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Valor inválido para '{key}': '{value}'"))
+}
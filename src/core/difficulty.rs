@@ -0,0 +1,86 @@
+use super::config::ChainConfig;
+
+pub fn leading_zero_bits(hash: &[u8]) -> u32 {
+    let mut bits = 0;
+
+    for &byte in hash {
+        if byte == 0 {
+            bits += 8;
+        } else {
+            bits += byte.leading_zeros();
+            break;
+        }
+    }
+
+    bits
+}
+
+pub fn work_for_hash(hash: &[u8]) -> u128 {
+    1u128 << leading_zero_bits(hash).min(127)
+}
+
+pub fn meets_difficulty(hash: &[u8], difficulty_bits: u32) -> bool {
+    leading_zero_bits(hash) >= difficulty_bits
+}
+
+pub fn retarget(previous_bits: u32, timespan_secs: u64, config: &ChainConfig) -> u32 {
+    let expected = config.target_interval_secs * (config.retarget_window as u64 - 1);
+    let actual = timespan_secs.max(1);
+
+    let bits = if actual.saturating_mul(4) <= expected {
+        previous_bits + 2
+    } else if actual.saturating_mul(2) <= expected {
+        previous_bits + 1
+    } else if actual >= expected.saturating_mul(4) {
+        previous_bits.saturating_sub(2)
+    } else if actual >= expected.saturating_mul(2) {
+        previous_bits.saturating_sub(1)
+    } else {
+        previous_bits
+    };
+
+    bits.clamp(config.min_difficulty_bits, config.max_difficulty_bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retarget_moves_towards_the_target_interval() {
+        let config = ChainConfig::default();
+        let expected = config.target_interval_secs * (config.retarget_window as u64 - 1);
+
+        assert_eq!(retarget(16, expected, &config), 16);
+        assert_eq!(retarget(16, expected / 2 + 1, &config), 16);
+        assert_eq!(retarget(16, expected * 2 - 1, &config), 16);
+        assert_eq!(retarget(16, expected / 2, &config), 17);
+        assert_eq!(retarget(16, expected / 4, &config), 18);
+        assert_eq!(retarget(16, 0, &config), 18);
+        assert_eq!(retarget(16, expected * 2, &config), 15);
+        assert_eq!(retarget(16, expected * 4, &config), 14);
+        assert_eq!(retarget(16, u64::MAX, &config), 14);
+    }
+
+    #[test]
+    fn retarget_stays_within_the_configured_bounds() {
+        let config = ChainConfig::default();
+
+        assert_eq!(
+            retarget(config.max_difficulty_bits, 0, &config),
+            config.max_difficulty_bits
+        );
+        assert_eq!(
+            retarget(config.max_difficulty_bits - 1, 0, &config),
+            config.max_difficulty_bits
+        );
+        assert_eq!(
+            retarget(config.min_difficulty_bits, u64::MAX, &config),
+            config.min_difficulty_bits
+        );
+        assert_eq!(
+            retarget(config.min_difficulty_bits + 1, u64::MAX, &config),
+            config.min_difficulty_bits
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use super::identity::Identity;
+use super::message::DirectMessage;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+pub const DIRECT_OVERHEAD: usize = 32 + 12 + 16;
+
+fn cipher(
+    identity: &Identity,
+    peer: &[u8; 32],
+    sender: &[u8; 32],
+    recipient: &[u8; 32],
+) -> Option<ChaCha20Poly1305> {
+    let shared = identity.shared_secret(peer)?;
+    let mut hasher = Sha256::new();
+    hasher.update(b"p2p-chat-dm");
+    hasher.update(shared);
+    hasher.update(sender);
+    hasher.update(recipient);
+    Some(ChaCha20Poly1305::new(Key::from_slice(&hasher.finalize())))
+}
+
+fn associated_data(sender: &[u8; 32], recipient: &[u8; 32]) -> Vec<u8> {
+    [sender.as_slice(), recipient.as_slice()].concat()
+}
+
+pub fn encrypt(identity: &Identity, recipient: &[u8; 32], text: &str) -> Option<DirectMessage> {
+    let sender = identity.public_key();
+    let cipher = cipher(identity, recipient, &sender, recipient)?;
+
+    let mut nonce = [0u8; 12];
+    rand::rng().fill(&mut nonce);
+
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: text.as_bytes(),
+                aad: &associated_data(&sender, recipient),
+            },
+        )
+        .ok()?;
+
+    Some(DirectMessage {
+        recipient: *recipient,
+        nonce,
+        ciphertext,
+    })
+}
+
+pub fn decrypt(identity: &Identity, sender: &[u8; 32], direct: &DirectMessage) -> Option<String> {
+    let own_key = identity.public_key();
+    let peer = if own_key == direct.recipient {
+        sender
+    } else if own_key == *sender {
+        &direct.recipient
+    } else {
+        return None;
+    };
+
+    let cipher = cipher(identity, peer, sender, &direct.recipient)?;
+    let plaintext = cipher
+        .decrypt(
+            Nonce::from_slice(&direct.nonce),
+            Payload {
+                msg: &direct.ciphertext,
+                aad: &associated_data(sender, &direct.recipient),
+            },
+        )
+        .ok()?;
+
+    String::from_utf8(plaintext).ok()
+}
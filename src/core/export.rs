@@ -0,0 +1,266 @@
+use super::archive::{Archive, ValidationError};
+use super::config::ChainConfig;
+use super::hex::{from_hex, to_hex};
+use super::message::{
+    Author, CHANNEL_CHAT_VERSION, CURRENT_CHAT_VERSION, Chat, ChatKind, KIND_TEXT,
+    LEGACY_CHAT_VERSION, SIGNED_CHAT_VERSION, TIMESTAMPED_CHAT_VERSION, TYPED_CHAT_VERSION,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveFormat {
+    Json,
+    Csv,
+    Raw,
+}
+
+impl ArchiveFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "json" => Some(ArchiveFormat::Json),
+            "csv" => Some(ArchiveFormat::Csv),
+            "raw" | "bin" => Some(ArchiveFormat::Raw),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &str) -> Option<Self> {
+        Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Self::from_name)
+    }
+}
+
+#[derive(Debug)]
+pub enum ArchiveFileError {
+    Io(std::io::Error),
+    Parse(String),
+    Invalid(ValidationError),
+}
+
+impl fmt::Display for ArchiveFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArchiveFileError::Io(e) => write!(f, "erro de E/S: {e}"),
+            ArchiveFileError::Parse(e) => write!(f, "formato inválido: {e}"),
+            ArchiveFileError::Invalid(e) => {
+                write!(f, "o arquivo de chats não passou na verificação ({e})")
+            }
+        }
+    }
+}
+
+impl From<std::io::Error> for ArchiveFileError {
+    fn from(e: std::io::Error) -> Self {
+        ArchiveFileError::Io(e)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ChatRecord {
+    #[serde(default)]
+    version: Option<u8>,
+    #[serde(default)]
+    kind: Option<String>,
+    message: String,
+    #[serde(default)]
+    payload: Option<String>,
+    #[serde(default)]
+    channel: Option<String>,
+    timestamp: Option<u64>,
+    #[serde(default)]
+    author_public_key: Option<String>,
+    #[serde(default)]
+    author_signature: Option<String>,
+    verification_code: String,
+    #[serde(alias = "md5_hash")]
+    hash: String,
+}
+
+impl From<&Chat> for ChatRecord {
+    fn from(chat: &Chat) -> Self {
+        ChatRecord {
+            version: Some(chat.version),
+            kind: Some(chat.kind.name().to_string()),
+            message: chat.message.clone(),
+            payload: (!chat.kind.body_is_text()).then(|| to_hex(&chat.body())),
+            channel: chat.channel.clone(),
+            timestamp: chat.timestamp,
+            author_public_key: chat.author.as_ref().map(|a| to_hex(&a.public_key)),
+            author_signature: chat.author.as_ref().map(|a| to_hex(&a.signature)),
+            verification_code: to_hex(&chat.verification_code),
+            hash: to_hex(&chat.hash),
+        }
+    }
+}
+
+impl TryFrom<ChatRecord> for Chat {
+    type Error = ArchiveFileError;
+
+    fn try_from(record: ChatRecord) -> Result<Self, Self::Error> {
+        let version = record.version.unwrap_or(if record.timestamp.is_some() {
+            TIMESTAMPED_CHAT_VERSION
+        } else {
+            LEGACY_CHAT_VERSION
+        });
+
+        let author = match (&record.author_public_key, &record.author_signature) {
+            (Some(public_key), Some(signature)) => Some(Author {
+                public_key: decode_array(public_key, "author_public_key")?,
+                signature: decode_array(signature, "author_signature")?,
+            }),
+            (None, None) => None,
+            _ => {
+                return Err(ArchiveFileError::Parse(
+                    "chave pública e assinatura do autor devem vir juntas".to_string(),
+                ));
+            }
+        };
+
+        let kind_code = match &record.kind {
+            Some(name) => {
+                ChatKind::code_from_name(name).ok_or_else(|| invalid_field(name, "kind"))?
+            }
+            None => KIND_TEXT,
+        };
+        let body = match &record.payload {
+            Some(payload) => decode_field(payload, "payload")?,
+            None => record.message.into_bytes(),
+        };
+        let (kind, message) = ChatKind::decode(kind_code, body).ok_or_else(|| {
+            ArchiveFileError::Parse("conteúdo do registro malformado".to_string())
+        })?;
+
+        let consistent = version <= CURRENT_CHAT_VERSION
+            && record.timestamp.is_some() == (version >= TIMESTAMPED_CHAT_VERSION)
+            && author.is_some() == (version >= SIGNED_CHAT_VERSION)
+            && record.channel.is_some() == (version >= CHANNEL_CHAT_VERSION)
+            && (kind == ChatKind::Text || version >= TYPED_CHAT_VERSION);
+        if !consistent {
+            return Err(ArchiveFileError::Parse(format!(
+                "campos incompatíveis com a versão {version} do registro"
+            )));
+        }
+
+        Ok(Chat {
+            version,
+            kind,
+            message,
+            channel: record.channel,
+            timestamp: record.timestamp,
+            author,
+            verification_code: decode_array(&record.verification_code, "verification_code")?,
+            hash: decode_field(&record.hash, "hash")?,
+        })
+    }
+}
+
+fn decode_field(text: &str, name: &str) -> Result<Vec<u8>, ArchiveFileError> {
+    from_hex(text).ok_or_else(|| invalid_field(text, name))
+}
+
+fn decode_array<const N: usize>(text: &str, name: &str) -> Result<[u8; N], ArchiveFileError> {
+    decode_field(text, name)?
+        .try_into()
+        .map_err(|_| invalid_field(text, name))
+}
+
+fn invalid_field(text: &str, name: &str) -> ArchiveFileError {
+    ArchiveFileError::Parse(format!("campo '{name}' inválido: '{text}'"))
+}
+
+#[derive(Serialize, Deserialize)]
+struct ArchiveRecord {
+    chats: Vec<ChatRecord>,
+}
+
+pub fn export_archive(
+    archive: &Archive,
+    format: ArchiveFormat,
+) -> Result<Vec<u8>, ArchiveFileError> {
+    match format {
+        ArchiveFormat::Json => {
+            let record = ArchiveRecord {
+                chats: archive.chats.iter().map(ChatRecord::from).collect(),
+            };
+            serde_json::to_vec_pretty(&record).map_err(|e| ArchiveFileError::Parse(e.to_string()))
+        }
+        ArchiveFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            for chat in &archive.chats {
+                writer
+                    .serialize(ChatRecord::from(chat))
+                    .map_err(|e| ArchiveFileError::Parse(e.to_string()))?;
+            }
+            writer
+                .into_inner()
+                .map_err(|e| ArchiveFileError::Parse(e.to_string()))
+        }
+        ArchiveFormat::Raw => Ok(archive.to_bytes()),
+    }
+}
+
+pub fn decode_archive(
+    data: &[u8],
+    format: ArchiveFormat,
+    config: ChainConfig,
+) -> Result<Archive, ArchiveFileError> {
+    let chats = match format {
+        ArchiveFormat::Json => {
+            let record: ArchiveRecord =
+                serde_json::from_slice(data).map_err(|e| ArchiveFileError::Parse(e.to_string()))?;
+            record
+                .chats
+                .into_iter()
+                .map(Chat::try_from)
+                .collect::<Result<Vec<_>, _>>()?
+        }
+        ArchiveFormat::Csv => {
+            let mut reader = csv::Reader::from_reader(data);
+            let mut chats = Vec::new();
+            for record in reader.deserialize::<ChatRecord>() {
+                let record = record.map_err(|e| ArchiveFileError::Parse(e.to_string()))?;
+                chats.push(Chat::try_from(record)?);
+            }
+            chats
+        }
+        ArchiveFormat::Raw => {
+            return Archive::from_bytes(data, config)
+                .ok_or_else(|| ArchiveFileError::Parse("dados binários truncados".to_string()));
+        }
+    };
+
+    Ok(Archive::with_chats(chats, config))
+}
+
+pub fn import_archive(
+    data: &[u8],
+    format: ArchiveFormat,
+    config: ChainConfig,
+) -> Result<Archive, ArchiveFileError> {
+    let archive = decode_archive(data, format, config)?;
+    archive.validate().map_err(ArchiveFileError::Invalid)?;
+
+    Ok(archive)
+}
+
+pub fn save_archive(
+    path: &str,
+    archive: &Archive,
+    format: ArchiveFormat,
+) -> Result<(), ArchiveFileError> {
+    fs::write(path, export_archive(archive, format)?)?;
+    Ok(())
+}
+
+pub fn load_archive(
+    path: &str,
+    format: ArchiveFormat,
+    config: ChainConfig,
+) -> Result<Archive, ArchiveFileError> {
+    import_archive(&fs::read(path)?, format, config)
+}
@@ -0,0 +1,80 @@
+use sha2::{Digest, Sha256};
+
+pub trait ChainHasher: Send + Sync {
+    fn digest(&self, data: &[u8]) -> Vec<u8>;
+    fn output_len(&self) -> usize;
+}
+
+pub struct Md5Hasher;
+
+impl ChainHasher for Md5Hasher {
+    fn digest(&self, data: &[u8]) -> Vec<u8> {
+        md5::compute(data).0.to_vec()
+    }
+
+    fn output_len(&self) -> usize {
+        16
+    }
+}
+
+pub struct Sha256Hasher;
+
+impl ChainHasher for Sha256Hasher {
+    fn digest(&self, data: &[u8]) -> Vec<u8> {
+        Sha256::digest(data).to_vec()
+    }
+
+    fn output_len(&self) -> usize {
+        32
+    }
+}
+
+pub struct Blake3Hasher;
+
+impl ChainHasher for Blake3Hasher {
+    fn digest(&self, data: &[u8]) -> Vec<u8> {
+        blake3::hash(data).as_bytes().to_vec()
+    }
+
+    fn output_len(&self) -> usize {
+        blake3::OUT_LEN
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HashFunction {
+    Md5,
+    Sha256,
+    Blake3,
+}
+
+impl HashFunction {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "md5" => Some(HashFunction::Md5),
+            "sha256" | "sha-256" => Some(HashFunction::Sha256),
+            "blake3" => Some(HashFunction::Blake3),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashFunction::Md5 => "MD5",
+            HashFunction::Sha256 => "SHA-256",
+            HashFunction::Blake3 => "BLAKE3",
+        }
+    }
+
+    pub fn hasher(&self) -> &'static dyn ChainHasher {
+        match self {
+            HashFunction::Md5 => &Md5Hasher,
+            HashFunction::Sha256 => &Sha256Hasher,
+            HashFunction::Blake3 => &Blake3Hasher,
+        }
+    }
+
+    pub fn digest_len(&self) -> usize {
+        self.hasher().output_len()
+    }
+}
@@ -0,0 +1,99 @@
+use super::hex::{from_hex, to_hex};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+pub struct Identity {
+    signing_key: SigningKey,
+}
+
+impl Identity {
+    pub fn generate() -> Self {
+        let mut seed = [0u8; 32];
+        rand::rng().fill(&mut seed);
+        Self::from_seed(&seed)
+    }
+
+    pub fn from_seed(seed: &[u8; 32]) -> Self {
+        Identity {
+            signing_key: SigningKey::from_bytes(seed),
+        }
+    }
+
+    pub fn load_or_create(path: &str) -> io::Result<Self> {
+        if Path::new(path).exists() {
+            return Self::load(path);
+        }
+
+        let identity = Self::generate();
+        identity.save(path)?;
+        Ok(identity)
+    }
+
+    pub fn load(path: &str) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let seed: [u8; 32] = from_hex(text.trim())
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("chave privada inválida em '{path}'"),
+                )
+            })?;
+
+        Ok(Self::from_seed(&seed))
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        if let Some(parent) = Path::new(path).parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        let mut file = options.open(path)?;
+        writeln!(file, "{}", to_hex(&self.signing_key.to_bytes()))
+    }
+
+    pub fn public_key(&self) -> [u8; 32] {
+        self.signing_key.verifying_key().to_bytes()
+    }
+
+    pub fn sign(&self, data: &[u8]) -> [u8; 64] {
+        self.signing_key.sign(data).to_bytes()
+    }
+
+    pub fn fingerprint(&self) -> String {
+        fingerprint(&self.public_key())
+    }
+
+    pub fn shared_secret(&self, peer_public_key: &[u8; 32]) -> Option<[u8; 32]> {
+        let peer = VerifyingKey::from_bytes(peer_public_key).ok()?;
+        let secret = StaticSecret::from(self.signing_key.to_scalar_bytes());
+        let shared = secret.diffie_hellman(&PublicKey::from(peer.to_montgomery().to_bytes()));
+        shared.was_contributory().then(|| shared.to_bytes())
+    }
+}
+
+pub fn verify_signature(public_key: &[u8; 32], data: &[u8], signature: &[u8; 64]) -> bool {
+    VerifyingKey::from_bytes(public_key)
+        .map(|key| {
+            key.verify_strict(data, &Signature::from_bytes(signature))
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+pub fn fingerprint(public_key: &[u8; 32]) -> String {
+    to_hex(&Sha256::digest(public_key)[..4])
+}
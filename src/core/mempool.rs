@@ -0,0 +1,75 @@
+use super::archive::Archive;
+use super::message::Chat;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+pub const MAX_PENDING_CHATS: usize = 1000;
+pub const MAX_PENDING_AGE: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Default)]
+pub struct Mempool {
+    pending: VecDeque<([u8; 32], Chat, Instant)>,
+}
+
+impl Mempool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contains(&self, content_hash: &[u8; 32]) -> bool {
+        self.pending.iter().any(|(hash, _, _)| hash == content_hash)
+    }
+
+    pub fn insert(&mut self, chat: Chat) -> bool {
+        let content_hash = chat.content_hash();
+        if self.contains(&content_hash) {
+            return false;
+        }
+
+        self.expire();
+        if self.pending.len() >= MAX_PENDING_CHATS {
+            self.pending.pop_front();
+        }
+
+        self.pending.push_back((content_hash, chat, Instant::now()));
+        true
+    }
+
+    pub fn pop(&mut self) -> Option<Chat> {
+        self.expire();
+        self.pending.pop_front().map(|(_, chat, _)| chat)
+    }
+
+    fn expire(&mut self) {
+        while self
+            .pending
+            .front()
+            .is_some_and(|(_, _, received)| received.elapsed() > MAX_PENDING_AGE)
+        {
+            self.pending.pop_front();
+        }
+    }
+
+    pub fn prune(&mut self, archive: &Archive) {
+        self.expire();
+        if self.pending.is_empty() {
+            return;
+        }
+
+        let archived = archive.content_hashes();
+        self.pending
+            .retain(|(content_hash, _, _)| !archived.contains(content_hash));
+    }
+
+    pub fn chats(&self) -> impl Iterator<Item = &Chat> {
+        self.pending.iter().map(|(_, chat, _)| chat)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
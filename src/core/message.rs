@@ -1,4 +1,7 @@
+use super::hex::to_hex;
+use sha2::{Digest, Sha256};
 use std::convert::From;
+use std::io::{self, Cursor, Read};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum MessageType {
@@ -7,6 +10,10 @@ pub enum MessageType {
     ArchiveRequest = 0x3,
     ArchiveResponse = 0x4,
     NotificationMessage = 0x5,
+    ChunkRequest = 0x6,
+    ChunkResponse = 0x7,
+    PendingChat = 0x8,
+    Hello = 0x9,
 }
 
 impl From<u8> for MessageType {
@@ -17,6 +24,10 @@ impl From<u8> for MessageType {
             0x3 => MessageType::ArchiveRequest,
             0x4 => MessageType::ArchiveResponse,
             0x5 => MessageType::NotificationMessage,
+            0x6 => MessageType::ChunkRequest,
+            0x7 => MessageType::ChunkResponse,
+            0x8 => MessageType::PendingChat,
+            0x9 => MessageType::Hello,
             _ => panic!("Invalid message type: {value}"),
         }
     }
@@ -24,52 +35,528 @@ impl From<u8> for MessageType {
 
 impl MessageType {
     pub fn is_valid_message(value: u8) -> bool {
-        (0x1..=0x5).contains(&value)
+        (0x1..=0x9).contains(&value)
     }
+}
+
+pub const EXTENDED_RECORD_MARKER: u8 = 0x00;
+pub const LEGACY_CHAT_VERSION: u8 = 0;
+pub const TIMESTAMPED_CHAT_VERSION: u8 = 1;
+pub const SIGNED_CHAT_VERSION: u8 = 2;
+pub const TYPED_CHAT_VERSION: u8 = 3;
+pub const CHANNEL_CHAT_VERSION: u8 = 4;
+pub const UNICODE_CHAT_VERSION: u8 = 5;
+pub const CURRENT_CHAT_VERSION: u8 = UNICODE_CHAT_VERSION;
+pub const GENESIS_CHAT_VERSION: u8 = UNICODE_CHAT_VERSION;
+
+pub const MAX_BODY_LEN: usize = 65536;
+
+pub const DEFAULT_CHANNEL: &str = "geral";
+
+pub const KIND_TEXT: u8 = 0;
+pub const KIND_NICK: u8 = 1;
+pub const KIND_DIRECT: u8 = 2;
+pub const KIND_ATTACHMENT: u8 = 3;
+pub const KIND_REPLY: u8 = 4;
+pub const KIND_REACTION: u8 = 5;
+pub const KIND_RETRACTION: u8 = 6;
+pub const KIND_GENESIS: u8 = 7;
+
+pub const REFERENCE_HASH_LEN: usize = 8;
+
+pub const CHUNK_SIZE: usize = 64 * 1024;
+pub const MAX_ATTACHMENT_NAME_LEN: usize = u8::MAX as usize;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirectMessage {
+    pub recipient: [u8; 32],
+    pub nonce: [u8; 12],
+    pub ciphertext: Vec<u8>,
+}
 
+impl DirectMessage {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&self.recipient);
+        bytes.extend_from_slice(&self.nonce);
+        bytes.extend_from_slice(&self.ciphertext);
+        bytes
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        if data.len() < 44 {
+            return None;
+        }
+
+        Some(DirectMessage {
+            recipient: data[..32].try_into().ok()?,
+            nonce: data[32..44].try_into().ok()?,
+            ciphertext: data[44..].to_vec(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttachmentManifest {
+    pub file_hash: [u8; 32],
+    pub size: u64,
+    pub name: String,
+    pub chunks: Vec<[u8; 32]>,
+}
+
+impl AttachmentManifest {
+    pub fn chunk_count(size: u64) -> usize {
+        size.div_ceil(CHUNK_SIZE as u64) as usize
+    }
+
+    pub fn id(&self) -> String {
+        to_hex(&self.file_hash[..4])
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&self.file_hash);
+        bytes.extend_from_slice(&self.size.to_be_bytes());
+        bytes.push(self.name.len() as u8);
+        bytes.extend_from_slice(self.name.as_bytes());
+        for chunk in &self.chunks {
+            bytes.extend_from_slice(chunk);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        let file_hash = data.get(..32)?.try_into().ok()?;
+        let size = u64::from_be_bytes(data.get(32..40)?.try_into().ok()?);
+        let name_len = *data.get(40)? as usize;
+        let name = String::from_utf8(data.get(41..41 + name_len)?.to_vec()).ok()?;
+
+        let hashes = &data[41 + name_len..];
+        if name.is_empty() || hashes.len() != Self::chunk_count(size).checked_mul(32)? {
+            return None;
+        }
+
+        Some(AttachmentManifest {
+            file_hash,
+            size,
+            name,
+            chunks: hashes
+                .chunks_exact(32)
+                .map(|chunk| chunk.try_into().unwrap())
+                .collect(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChatReference {
+    pub index: u32,
+    pub hash_prefix: [u8; REFERENCE_HASH_LEN],
+}
+
+impl ChatReference {
+    const LEN: usize = 4 + REFERENCE_HASH_LEN;
+
+    pub fn to(index: usize, chat: &Chat) -> Self {
+        let mut hash_prefix = [0u8; REFERENCE_HASH_LEN];
+        let len = chat.hash.len().min(REFERENCE_HASH_LEN);
+        hash_prefix[..len].copy_from_slice(&chat.hash[..len]);
+        ChatReference {
+            index: index as u32,
+            hash_prefix,
+        }
+    }
+
+    pub fn matches(&self, chat: &Chat) -> bool {
+        chat.hash.starts_with(&self.hash_prefix)
+    }
+
+    pub fn to_bytes(self) -> Vec<u8> {
+        let mut bytes = self.index.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&self.hash_prefix);
+        bytes
+    }
+
+    fn decode(body: &[u8]) -> Option<(Self, String)> {
+        let reference = ChatReference {
+            index: u32::from_be_bytes(body.get(..4)?.try_into().ok()?),
+            hash_prefix: body.get(4..Self::LEN)?.try_into().ok()?,
+        };
+        let text = String::from_utf8(body[Self::LEN..].to_vec()).ok()?;
+        Some((reference, text))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChatKind {
+    Text,
+    Nick,
+    Direct(DirectMessage),
+    Attachment(AttachmentManifest),
+    Reply(ChatReference),
+    Reaction(ChatReference),
+    Retraction(ChatReference),
+    Genesis,
+}
+
+impl ChatKind {
+    pub fn code(&self) -> u8 {
+        match self {
+            ChatKind::Text => KIND_TEXT,
+            ChatKind::Nick => KIND_NICK,
+            ChatKind::Direct(_) => KIND_DIRECT,
+            ChatKind::Attachment(_) => KIND_ATTACHMENT,
+            ChatKind::Reply(_) => KIND_REPLY,
+            ChatKind::Reaction(_) => KIND_REACTION,
+            ChatKind::Retraction(_) => KIND_RETRACTION,
+            ChatKind::Genesis => KIND_GENESIS,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChatKind::Text => "text",
+            ChatKind::Nick => "nick",
+            ChatKind::Direct(_) => "direct",
+            ChatKind::Attachment(_) => "attachment",
+            ChatKind::Reply(_) => "reply",
+            ChatKind::Reaction(_) => "reaction",
+            ChatKind::Retraction(_) => "retraction",
+            ChatKind::Genesis => "genesis",
+        }
+    }
+
+    pub fn code_from_name(name: &str) -> Option<u8> {
+        match name {
+            "text" => Some(KIND_TEXT),
+            "nick" => Some(KIND_NICK),
+            "direct" => Some(KIND_DIRECT),
+            "attachment" => Some(KIND_ATTACHMENT),
+            "reply" => Some(KIND_REPLY),
+            "reaction" => Some(KIND_REACTION),
+            "retraction" => Some(KIND_RETRACTION),
+            "genesis" => Some(KIND_GENESIS),
+            _ => None,
+        }
+    }
+
+    pub fn reference(&self) -> Option<&ChatReference> {
+        match self {
+            ChatKind::Reply(reference)
+            | ChatKind::Reaction(reference)
+            | ChatKind::Retraction(reference) => Some(reference),
+            _ => None,
+        }
+    }
+
+    pub fn body_is_text(&self) -> bool {
+        matches!(self, ChatKind::Text | ChatKind::Nick | ChatKind::Genesis)
+    }
+
+    pub fn decode(code: u8, body: Vec<u8>) -> Option<(Self, String)> {
+        match code {
+            KIND_TEXT => Some((ChatKind::Text, String::from_utf8(body).ok()?)),
+            KIND_NICK => Some((ChatKind::Nick, String::from_utf8(body).ok()?)),
+            KIND_GENESIS => Some((ChatKind::Genesis, String::from_utf8(body).ok()?)),
+            KIND_DIRECT => Some((
+                ChatKind::Direct(DirectMessage::from_bytes(&body)?),
+                String::new(),
+            )),
+            KIND_ATTACHMENT => Some((
+                ChatKind::Attachment(AttachmentManifest::from_bytes(&body)?),
+                String::new(),
+            )),
+            KIND_REPLY => ChatReference::decode(&body)
+                .map(|(reference, text)| (ChatKind::Reply(reference), text)),
+            KIND_REACTION => ChatReference::decode(&body)
+                .map(|(reference, text)| (ChatKind::Reaction(reference), text)),
+            KIND_RETRACTION => match ChatReference::decode(&body)? {
+                (reference, text) if text.is_empty() => {
+                    Some((ChatKind::Retraction(reference), text))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Author {
+    pub public_key: [u8; 32],
+    pub signature: [u8; 64],
 }
 
 #[derive(Debug, Clone)]
 pub struct Chat {
+    pub version: u8,
+    pub kind: ChatKind,
     pub message: String,
+    pub channel: Option<String>,
+    pub timestamp: Option<u64>,
+    pub author: Option<Author>,
     pub verification_code: [u8; 16],
-    pub md5_hash: [u8; 16],
+    pub hash: Vec<u8>,
 }
 
 impl Chat {
-    pub fn to_bytes(&self) -> Vec<u8> {
+    pub fn signed_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::new();
-        bytes.push(self.message.len() as u8);
-        bytes.extend_from_slice(self.message.as_bytes());
+        if self.version >= TIMESTAMPED_CHAT_VERSION {
+            bytes.push(EXTENDED_RECORD_MARKER);
+            bytes.push(self.version);
+            bytes.extend_from_slice(&self.timestamp.unwrap_or(0).to_be_bytes());
+        }
+        if self.version >= TYPED_CHAT_VERSION {
+            bytes.push(self.kind.code());
+        }
+        if self.version >= CHANNEL_CHAT_VERSION {
+            let channel = self.channel();
+            bytes.push(channel.len() as u8);
+            bytes.extend_from_slice(channel.as_bytes());
+        }
+        if self.version >= SIGNED_CHAT_VERSION {
+            let public_key = self.author.as_ref().map(|a| a.public_key);
+            bytes.extend_from_slice(&public_key.unwrap_or([0u8; 32]));
+        }
+        let body = self.body();
+        if self.version >= UNICODE_CHAT_VERSION {
+            write_varint(&mut bytes, body.len() as u64);
+        } else {
+            bytes.push(body.len() as u8);
+        }
+        bytes.extend_from_slice(&body);
+        bytes
+    }
+
+    pub fn channel(&self) -> &str {
+        self.channel.as_deref().unwrap_or(DEFAULT_CHANNEL)
+    }
+
+    pub fn body(&self) -> Vec<u8> {
+        match &self.kind {
+            ChatKind::Direct(direct) => direct.to_bytes(),
+            ChatKind::Attachment(manifest) => manifest.to_bytes(),
+            ChatKind::Reply(reference)
+            | ChatKind::Reaction(reference)
+            | ChatKind::Retraction(reference) => {
+                [reference.to_bytes(), self.message.as_bytes().to_vec()].concat()
+            }
+            _ => self.message.as_bytes().to_vec(),
+        }
+    }
+
+    pub fn unmined_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.signed_bytes();
+        if self.version >= SIGNED_CHAT_VERSION {
+            let signature = self.author.as_ref().map(|a| a.signature);
+            bytes.extend_from_slice(&signature.unwrap_or([0u8; 64]));
+        }
+        bytes
+    }
+
+    pub fn content_hash(&self) -> [u8; 32] {
+        Sha256::digest(self.unmined_bytes()).into()
+    }
+
+    pub fn hashed_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.unmined_bytes();
         bytes.extend_from_slice(&self.verification_code);
-        bytes.extend_from_slice(&self.md5_hash);
         bytes
     }
 
-    pub fn from_bytes(data: &[u8]) -> Option<(Self, usize)> {
-        if data.is_empty() {
-            return None;
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.hashed_bytes();
+        bytes.extend_from_slice(&self.hash);
+        bytes
+    }
+
+    pub fn read_from(reader: &mut impl Read, hash_len: usize) -> io::Result<Self> {
+        let mut chat = Self::read_unmined(reader)?;
+        chat.hash = vec![0u8; hash_len];
+
+        reader.read_exact(&mut chat.verification_code)?;
+        reader.read_exact(&mut chat.hash)?;
+        Ok(chat)
+    }
+
+    pub fn read_unmined(reader: &mut impl Read) -> io::Result<Self> {
+        let mut first_byte = [0u8; 1];
+        reader.read_exact(&mut first_byte)?;
+
+        let mut version = LEGACY_CHAT_VERSION;
+        let mut kind_code = KIND_TEXT;
+        let mut timestamp = None;
+        let mut channel = None;
+        let mut public_key = None;
+        let mut body_len = first_byte[0] as usize;
+
+        if first_byte[0] == EXTENDED_RECORD_MARKER {
+            let mut version_buf = [0u8; 1];
+            reader.read_exact(&mut version_buf)?;
+            version = version_buf[0];
+            if !(TIMESTAMPED_CHAT_VERSION..=CURRENT_CHAT_VERSION).contains(&version) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("versão de registro de chat desconhecida: {version}"),
+                ));
+            }
+
+            let mut timestamp_buf = [0u8; 8];
+            reader.read_exact(&mut timestamp_buf)?;
+            timestamp = Some(u64::from_be_bytes(timestamp_buf));
+
+            if version >= TYPED_CHAT_VERSION {
+                let mut kind_buf = [0u8; 1];
+                reader.read_exact(&mut kind_buf)?;
+                kind_code = kind_buf[0];
+            }
+
+            if version >= CHANNEL_CHAT_VERSION {
+                let mut channel_len = [0u8; 1];
+                reader.read_exact(&mut channel_len)?;
+                let mut channel_buf = vec![0u8; channel_len[0] as usize];
+                reader.read_exact(&mut channel_buf)?;
+                channel = Some(
+                    String::from_utf8(channel_buf)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+                );
+            }
+
+            if version >= SIGNED_CHAT_VERSION {
+                let mut key_buf = [0u8; 32];
+                reader.read_exact(&mut key_buf)?;
+                public_key = Some(key_buf);
+            }
+
+            if version >= UNICODE_CHAT_VERSION {
+                body_len = read_varint(reader)? as usize;
+                if body_len > MAX_BODY_LEN {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("conteúdo de registro com {body_len} bytes excede o máximo"),
+                    ));
+                }
+            } else {
+                let mut len_buf = [0u8; 1];
+                reader.read_exact(&mut len_buf)?;
+                body_len = len_buf[0] as usize;
+            }
         }
 
-        let msg_len = data[0] as usize;
-        if data.len() < 1 + msg_len + 32 {
-            return None;
+        let mut body = vec![0u8; body_len];
+        reader.read_exact(&mut body)?;
+
+        let author = match public_key {
+            Some(public_key) => {
+                let mut signature = [0u8; 64];
+                reader.read_exact(&mut signature)?;
+                Some(Author {
+                    public_key,
+                    signature,
+                })
+            }
+            None => None,
+        };
+
+        let (kind, message) = ChatKind::decode(kind_code, body).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("registro de chat do tipo {kind_code} malformado"),
+            )
+        })?;
+
+        Ok(Chat {
+            version,
+            kind,
+            message,
+            channel,
+            timestamp,
+            author,
+            verification_code: [0u8; 16],
+            hash: Vec::new(),
+        })
+    }
+
+    pub fn from_bytes(data: &[u8], hash_len: usize) -> Option<(Self, usize)> {
+        let mut cursor = Cursor::new(data);
+        let chat = Self::read_from(&mut cursor, hash_len).ok()?;
+        Some((chat, cursor.position() as usize))
+    }
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn read_varint(reader: &mut impl Read) -> io::Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0u8; 1];
+        reader.read_exact(&mut byte)?;
+        value |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            if byte[0] == 0 && shift > 0 {
+                break;
+            }
+            return Ok(value);
         }
+    }
 
-        let message = String::from_utf8(data[1..1 + msg_len].to_vec()).ok()?;
-        let mut verification_code = [0u8; 16];
-        let mut md5_hash = [0u8; 16];
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "comprimento varint inválido",
+    ))
+}
 
-        verification_code.copy_from_slice(&data[1 + msg_len..1 + msg_len + 16]);
-        md5_hash.copy_from_slice(&data[1 + msg_len + 16..1 + msg_len + 32]);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        Some((
-            Chat {
-                message,
-                verification_code,
-                md5_hash,
-            },
-            1 + msg_len + 32,
-        ))
+    fn varint(value: u64) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_varint(&mut bytes, value);
+        bytes
+    }
+
+    #[test]
+    fn read_varint_accepts_canonical_encodings() {
+        for value in [0, 1, 0x7f, 0x80, 300, MAX_BODY_LEN as u64, u64::MAX] {
+            let bytes = varint(value);
+            assert_eq!(read_varint(&mut bytes.as_slice()).unwrap(), value);
+        }
+        assert_eq!(varint(300), [0xac, 0x02]);
+    }
+
+    #[test]
+    fn read_varint_rejects_overlong_encodings() {
+        for bytes in [&[0x80, 0x00][..], &[0x81, 0x80, 0x00], &[0xff; 11]] {
+            let error = read_varint(&mut &bytes[..]).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+        assert!(read_varint(&mut &[0x80][..]).is_err());
+    }
+
+    #[test]
+    fn body_length_over_the_maximum_is_rejected() {
+        let header = |body_len: u64| {
+            let mut bytes = vec![EXTENDED_RECORD_MARKER, UNICODE_CHAT_VERSION];
+            bytes.extend_from_slice(&0u64.to_be_bytes());
+            bytes.extend_from_slice(&[KIND_TEXT, 0]);
+            bytes.extend_from_slice(&[0u8; 32]);
+            bytes.extend_from_slice(&varint(body_len));
+            bytes
+        };
+
+        let error =
+            Chat::read_unmined(&mut header(MAX_BODY_LEN as u64 + 1).as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let error = Chat::read_unmined(&mut header(MAX_BODY_LEN as u64).as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
     }
 }
@@ -0,0 +1,167 @@
+use rand::rngs::{StdRng, ThreadRng};
+use rand::{Rng, SeedableRng};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub const PROGRESS_INTERVAL: u64 = 1024;
+
+static CURRENT: Mutex<Option<MiningProgress>> = Mutex::new(None);
+static LAST_HASHRATE: Mutex<Option<f64>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy)]
+pub struct MiningProgress {
+    pub difficulty_bits: u32,
+    pub attempts: u64,
+    pub started: Instant,
+}
+
+impl MiningProgress {
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    pub fn hashrate(&self) -> f64 {
+        let secs = self.elapsed().as_secs_f64();
+        if secs > 0.0 {
+            self.attempts as f64 / secs
+        } else {
+            0.0
+        }
+    }
+
+    pub fn expected_attempts(&self) -> f64 {
+        2f64.powi(self.difficulty_bits as i32)
+    }
+
+    pub fn eta(&self) -> Option<Duration> {
+        let hashrate = self.hashrate();
+        if hashrate > 0.0 {
+            Duration::try_from_secs_f64(self.expected_attempts() / hashrate).ok()
+        } else {
+            None
+        }
+    }
+}
+
+pub struct MiningJob {
+    attempts: u64,
+}
+
+impl MiningJob {
+    pub fn start(difficulty_bits: u32) -> Self {
+        *CURRENT.lock().unwrap() = Some(MiningProgress {
+            difficulty_bits,
+            attempts: 0,
+            started: Instant::now(),
+        });
+        MiningJob { attempts: 0 }
+    }
+
+    pub fn attempt(&mut self) {
+        self.attempts += 1;
+        if self.attempts.is_multiple_of(PROGRESS_INTERVAL)
+            && let Some(progress) = CURRENT.lock().unwrap().as_mut()
+        {
+            progress.attempts = self.attempts;
+        }
+    }
+}
+
+impl Drop for MiningJob {
+    fn drop(&mut self) {
+        if let Some(mut progress) = CURRENT.lock().unwrap().take() {
+            progress.attempts = self.attempts;
+            if progress.elapsed() > Duration::ZERO {
+                *LAST_HASHRATE.lock().unwrap() = Some(progress.hashrate());
+            }
+        }
+    }
+}
+
+pub fn current() -> Option<MiningProgress> {
+    *CURRENT.lock().unwrap()
+}
+
+pub fn last_hashrate() -> Option<f64> {
+    *LAST_HASHRATE.lock().unwrap()
+}
+
+pub fn format_hashrate(hashrate: f64) -> String {
+    if hashrate >= 1e6 {
+        format!("{:.2} MH/s", hashrate / 1e6)
+    } else if hashrate >= 1e3 {
+        format!("{:.1} kH/s", hashrate / 1e3)
+    } else {
+        format!("{hashrate:.0} H/s")
+    }
+}
+
+pub trait NonceSource {
+    fn fill(&mut self, code: &mut [u8; 16]);
+}
+
+pub struct RandomNonce(ThreadRng);
+
+impl NonceSource for RandomNonce {
+    fn fill(&mut self, code: &mut [u8; 16]) {
+        self.0.fill(code);
+    }
+}
+
+pub struct SequentialNonce {
+    next: u128,
+}
+
+impl NonceSource for SequentialNonce {
+    fn fill(&mut self, code: &mut [u8; 16]) {
+        *code = self.next.to_be_bytes();
+        self.next = self.next.wrapping_add(1);
+    }
+}
+
+pub struct SeededNonce(StdRng);
+
+impl NonceSource for SeededNonce {
+    fn fill(&mut self, code: &mut [u8; 16]) {
+        self.0.fill(code);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NonceMode {
+    #[default]
+    Random,
+    Sequential,
+    Seeded(u64),
+}
+
+impl NonceMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "random" => Some(NonceMode::Random),
+            "sequential" => Some(NonceMode::Sequential),
+            other => other
+                .strip_prefix("seed:")
+                .and_then(|seed| seed.parse().ok())
+                .map(NonceMode::Seeded),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            NonceMode::Random => "random".to_string(),
+            NonceMode::Sequential => "sequential".to_string(),
+            NonceMode::Seeded(seed) => format!("seed:{seed}"),
+        }
+    }
+
+    pub fn source(&self, index: usize) -> Box<dyn NonceSource> {
+        match *self {
+            NonceMode::Random => Box::new(RandomNonce(rand::rng())),
+            NonceMode::Sequential => Box::new(SequentialNonce { next: 0 }),
+            NonceMode::Seeded(seed) => Box::new(SeededNonce(StdRng::seed_from_u64(
+                seed.wrapping_add(index as u64),
+            ))),
+        }
+    }
+}
@@ -1,2 +1,13 @@
 pub mod archive;
+pub mod attachment;
+pub mod checkpoint;
+pub mod config;
+pub mod difficulty;
+pub mod direct;
+pub mod export;
+pub mod hasher;
+pub mod hex;
+pub mod identity;
+pub mod mempool;
 pub mod message;
+pub mod mining;
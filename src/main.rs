@@ -1,44 +1,112 @@
+mod cli;
 mod constants;
 mod core;
 mod logger;
 mod network;
 
+use cli::args::{NodeOptions, parse_file_args};
+use cli::channels::ChannelState;
+use cli::console;
+use cli::history::{HistoryOptions, HistoryView, format_timestamp};
+use cli::progress::{self, MiningIndicator, with_indicator};
 use constants::TCP_PORT;
-use network::P2PNode;
+use core::archive::BatchPolicy;
+use core::export;
+use core::hex::to_hex;
+use core::identity::Identity;
+use core::mining::{self, format_hashrate};
+use network::{NodeEvent, P2PNode};
+use regex::Regex;
+use std::collections::HashSet;
 use std::env;
-use std::io::{self, BufRead, Write};
+use std::fs;
+use std::io::{self, BufRead};
 use std::net::Ipv4Addr;
+use std::path::Path;
+use std::process;
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(15);
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    let initial_peer = args.get(1).cloned();
+
+    if args.get(1).map(String::as_str) == Some("verify") {
+        let verify_args: Vec<&str> = args[2..].iter().map(String::as_str).collect();
+        process::exit(cli::verify::run(&verify_args));
+    }
+
+    if args.get(1).map(String::as_str) == Some("bench") {
+        let bench_args: Vec<&str> = args[2..].iter().map(String::as_str).collect();
+        process::exit(cli::bench::run(&bench_args));
+    }
+
+    let node_args: Vec<&str> = args[1..].iter().map(String::as_str).collect();
+    let options = match NodeOptions::parse(&node_args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}");
+            eprintln!(
+                "Uso: p2p_chat_blockchain [--config <arquivo>] [--difficulty <bits>] [--identity <arquivo>] [--miner] [--nonce <modo>] [--timestamp <unix>] [--checkpoints <arquivo>] [ip_do_peer]"
+            );
+            process::exit(2);
+        }
+    };
+
+    let identity = match Identity::load_or_create(options.identity_path) {
+        Ok(identity) => identity,
+        Err(e) => {
+            eprintln!(
+                "Erro ao carregar identidade '{}': {e}",
+                options.identity_path
+            );
+            process::exit(2);
+        }
+    };
 
     logger::set_log_level(logger::LogLevel::Off);
 
     logger::info("Iniciando Chat P2P com Blockchain...");
-    if let Some(peer) = &initial_peer {
+    if let Some(peer) = options.initial_peer {
         logger::info(&format!("Tentando conectar ao peer inicial: {peer}"));
     } else {
         logger::info("Nenhum peer inicial especificado. Aguardando conexões...");
     }
 
-    let node = P2PNode::new();
+    let node = P2PNode::new(options.config, identity);
+    {
+        let mut archive = node.archive.write().unwrap();
+        archive.nonce_mode = options.nonce_mode;
+        archive.fixed_timestamp = options.fixed_timestamp;
+        archive.checkpoints = options.checkpoints;
+    }
+    let channels = Arc::new(Mutex::new(ChannelState::new()));
+    let events = node.subscribe();
+    let notifier_node = node.clone_state();
+    let notifier_channels = Arc::clone(&channels);
+    thread::spawn(move || chat_notifier(&notifier_node, &notifier_channels, events));
     node.start_listener();
+    if options.miner {
+        logger::info("Modo minerador ativado: mensagens pendentes serão incluídas no arquivo.");
+        node.start_miner();
+    }
 
-    if let Some(peer_addr) = initial_peer {
-        node.connect_to_peer(&peer_addr);
+    if let Some(peer_addr) = options.initial_peer {
+        node.connect_to_peer(peer_addr);
     }
 
-    user_input_loop(&node);
+    user_input_loop(&node, &channels);
 }
 
-fn user_input_loop(node: &P2PNode) {
+fn user_input_loop(node: &P2PNode, channels: &Mutex<ChannelState>) {
     print_help();
     let stdin = io::stdin();
 
     loop {
-        print!("> ");
-        io::stdout().flush().unwrap();
+        console::print_prompt();
 
         let mut input = String::new();
         if stdin.lock().read_line(&mut input).is_err() {
@@ -54,12 +122,29 @@ fn user_input_loop(node: &P2PNode) {
         let args = &parts[1..];
 
         match command {
-            "c" | "chat" => handle_chat(node, args),
-            "h" | "history" => handle_history(node),
+            "c" | "chat" => handle_chat(node, channels, args),
+            "post" => handle_post(node, channels, args),
+            "mempool" => handle_mempool(node),
+            "h" | "history" => handle_history(node, args),
+            "search" => handle_search(node, args),
+            "nick" => handle_nick(node, args),
+            "dm" => handle_dm(node, args),
+            "reply" => handle_reply(node, args),
+            "react" => handle_react(node, args),
+            "retract" => handle_retract(node, args),
+            "j" | "join" => handle_join(channels, args),
+            "leave" => handle_leave(channels, args),
+            "channels" => handle_channels(node, channels),
             "p" | "peers" => handle_peers(node),
             "s" | "status" => handle_status(node),
             "a" | "addpeer" => handle_addpeer(node, args),
-            "f" | "filechat" => handle_filechat(node, args),
+            "f" | "filechat" => handle_filechat(node, channels, args),
+            "attach" => handle_attach(node, channels, args),
+            "download" => handle_download(node, args),
+            "export" => handle_export(node, args),
+            "import" => handle_import(node, args),
+            "mute" => handle_mute(true),
+            "unmute" => handle_mute(false),
             "?" | "help" => print_help(),
             "q" | "quit" => break,
             _ => {
@@ -71,30 +156,278 @@ fn user_input_loop(node: &P2PNode) {
     }
 }
 
-fn handle_chat(node: &P2PNode, args: &[&str]) {
+fn handle_chat(node: &P2PNode, channels: &Mutex<ChannelState>, args: &[&str]) {
     if args.is_empty() {
         eprintln!("Uso: chat <mensagem>");
         return;
     }
 
     let message = args.join(" ");
-    let mut archive = node.archive.write().unwrap();
+    let channel = channels.lock().unwrap().current().to_string();
+    with_indicator(|| {
+        let mut archive = node.archive.write().unwrap();
+        archive.add_message(message, &channel, &node.identity)
+    });
+}
+
+fn handle_post(node: &P2PNode, channels: &Mutex<ChannelState>, args: &[&str]) {
+    if args.is_empty() {
+        eprintln!("Uso: post <mensagem>");
+        return;
+    }
+
+    let channel = channels.lock().unwrap().current().to_string();
+    let archive = node.archive.read().unwrap();
+    let Some(chat) = archive.prepare_message(args.join(" "), &channel, &node.identity) else {
+        return;
+    };
+    drop(archive);
+
+    match node.submit_pending(chat) {
+        Ok(peers) => println!(
+            "Mensagem enviada para a mempool ({peers} peer(s)); aguardando um nó minerador."
+        ),
+        Err(e) => eprintln!("Erro: {e}."),
+    }
+}
+
+fn handle_mempool(node: &P2PNode) {
+    let mempool = node.mempool.lock().unwrap();
+    if mempool.is_empty() {
+        println!("Nenhuma mensagem pendente.");
+        return;
+    }
+
+    let archive = node.archive.read().unwrap();
+    let view = HistoryView::new(&archive, &node.identity);
+    println!("--- Mensagens Pendentes ({}) ---", mempool.len());
+    for chat in mempool.chats() {
+        println!(
+            "({}) {} {}",
+            to_hex(&chat.content_hash()[..6]),
+            format_timestamp(chat.timestamp),
+            view.render(archive.len(), chat)
+        );
+    }
+    println!("-------------------------------");
+}
+
+fn handle_join(channels: &Mutex<ChannelState>, args: &[&str]) {
+    let [name] = args else {
+        eprintln!("Uso: join <canal>");
+        return;
+    };
+
+    match channels.lock().unwrap().join(name) {
+        Ok(channel) => println!("Agora enviando mensagens para #{channel}."),
+        Err(e) => eprintln!("{e}"),
+    }
+}
+
+fn handle_leave(channels: &Mutex<ChannelState>, args: &[&str]) {
+    let mut channels = channels.lock().unwrap();
+    let name = match args {
+        [] => channels.current().to_string(),
+        [name] => name.to_string(),
+        _ => {
+            eprintln!("Uso: leave [canal]");
+            return;
+        }
+    };
+
+    match channels.leave(&name) {
+        Ok(channel) => println!(
+            "Você saiu de #{channel}. Canal atual: #{}.",
+            channels.current()
+        ),
+        Err(e) => eprintln!("{e}"),
+    }
+}
+
+fn handle_channels(node: &P2PNode, channels: &Mutex<ChannelState>) {
+    let archive = node.archive.read().unwrap();
+    channels.lock().unwrap().print(&archive);
+}
+
+fn handle_nick(node: &P2PNode, args: &[&str]) {
+    let [nick] = args else {
+        eprintln!("Uso: nick <apelido>");
+        return;
+    };
+
+    let registered = with_indicator(|| {
+        let mut archive = node.archive.write().unwrap();
+        archive.register_nick(nick.to_string(), &node.identity)
+    });
+    if registered {
+        println!("Apelido '{nick}' registrado.");
+    }
+}
+
+fn handle_dm(node: &P2PNode, args: &[&str]) {
+    let [recipient, words @ ..] = args else {
+        eprintln!("Uso: dm <destinatário> <mensagem>");
+        return;
+    };
+    if words.is_empty() {
+        eprintln!("Uso: dm <destinatário> <mensagem>");
+        return;
+    }
+
+    let Some(recipient_key) = node.archive.read().unwrap().resolve_key(recipient) else {
+        eprintln!(
+            "Destinatário desconhecido: '{recipient}'. Use um apelido, uma impressão digital ou uma chave pública em hexadecimal."
+        );
+        return;
+    };
+
+    with_indicator(|| {
+        let mut archive = node.archive.write().unwrap();
+        archive.send_direct(&recipient_key, &words.join(" "), &node.identity)
+    });
+}
+
+fn handle_reply(node: &P2PNode, args: &[&str]) {
+    let Some((target, words)) = parse_target(args).filter(|(_, words)| !words.is_empty()) else {
+        eprintln!("Uso: reply <índice> <mensagem>");
+        return;
+    };
+
+    with_indicator(|| {
+        let mut archive = node.archive.write().unwrap();
+        archive.add_reply(target, words.join(" "), &node.identity)
+    });
+}
 
-    archive.add_message(message);
+fn handle_react(node: &P2PNode, args: &[&str]) {
+    let Some((target, [reaction])) = parse_target(args) else {
+        eprintln!("Uso: react <índice> <reação>");
+        return;
+    };
+
+    with_indicator(|| {
+        let mut archive = node.archive.write().unwrap();
+        archive.add_reaction(target, reaction.to_string(), &node.identity)
+    });
 }
 
-fn handle_history(node: &P2PNode) {
+fn handle_retract(node: &P2PNode, args: &[&str]) {
+    let Some((target, [])) = parse_target(args) else {
+        eprintln!("Uso: retract <índice>");
+        return;
+    };
+
+    let retracted = with_indicator(|| {
+        let mut archive = node.archive.write().unwrap();
+        archive.retract(target, &node.identity)
+    });
+    if retracted {
+        println!("Mensagem [{target}] retirada.");
+    }
+}
+
+fn parse_target<'a>(args: &'a [&'a str]) -> Option<(usize, &'a [&'a str])> {
+    let (target, rest) = args.split_first()?;
+    Some((target.parse().ok()?, rest))
+}
+
+fn handle_history(node: &P2PNode, args: &[&str]) {
+    let options = match HistoryOptions::parse(args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}");
+            eprintln!(
+                "Uso: history [<inicio>..<fim>] [--last <n>] [--channel <canal>] [--thread <índice>] [-r] [-v]"
+            );
+            return;
+        }
+    };
+
     let archive = node.archive.read().unwrap();
     if archive.chats.is_empty() {
         println!("O histórico de chats está vazio.");
-    } else {
-        println!("--- Histórico de Chats ({} mensagens) ---", archive.len());
-        for (i, chat) in archive.chats.iter().enumerate() {
-            let width = archive.len().to_string().len();
-            println!("[{:0w$}] {}", i, chat.message, w = width);
+        return;
+    }
+
+    let width = archive.len().to_string().len();
+    let view = HistoryView::new(&archive, &node.identity).show_retracted(options.show_retracted);
+
+    if let Some(index) = options.thread {
+        if index >= archive.len() {
+            eprintln!("Índice fora do histórico: {index}");
+            return;
         }
+
+        println!("--- Conversa da mensagem [{index}] ---");
+        view.print_thread(index, width, options.verbose);
         println!("-------------------------------------------");
+        return;
+    }
+
+    match &options.channel {
+        Some(channel) => {
+            let selected = options.select(&archive);
+            println!(
+                "--- Histórico de #{channel} ({} de {} mensagens) ---",
+                selected.len(),
+                archive.len()
+            );
+            for i in selected {
+                view.print_chat(i, &archive.chats[i], width, options.verbose);
+            }
+        }
+        None => {
+            let range = options.resolve(archive.len());
+            println!(
+                "--- Histórico de Chats ({}..{} de {} mensagens) ---",
+                range.start,
+                range.end,
+                archive.len()
+            );
+            for i in range {
+                view.print_chat(i, &archive.chats[i], width, options.verbose);
+            }
+        }
+    }
+    println!("-------------------------------------------");
+}
+
+fn handle_search(node: &P2PNode, args: &[&str]) {
+    let verbose = args.iter().any(|&a| a == "-v" || a == "--verbose");
+    let pattern = args
+        .iter()
+        .filter(|&&a| a != "-v" && a != "--verbose")
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if pattern.is_empty() {
+        eprintln!("Uso: search <regex> [-v]");
+        return;
+    }
+
+    let regex = match Regex::new(&pattern) {
+        Ok(regex) => regex,
+        Err(e) => {
+            eprintln!("Expressão regular inválida: {e}");
+            return;
+        }
+    };
+
+    let archive = node.archive.read().unwrap();
+    let width = archive.len().to_string().len();
+    let view = HistoryView::new(&archive, &node.identity);
+    let retracted = archive.retracted();
+    let mut matches = 0;
+
+    for (i, chat) in archive.chats.iter().enumerate() {
+        if !retracted.contains(&i) && regex.is_match(&chat.message) {
+            view.print_chat(i, chat, width, verbose);
+            matches += 1;
+        }
     }
+
+    println!("{matches} mensagem(ns) encontrada(s).");
 }
 
 fn handle_peers(node: &P2PNode) {
@@ -112,12 +445,47 @@ fn handle_peers(node: &P2PNode) {
 }
 
 fn handle_status(node: &P2PNode) {
+    let current_mining = mining::current();
+    let last_hashrate = mining::last_hashrate();
     let peers_count = node.peers.lock().unwrap().get_ips().len();
-    let archive_len = node.archive.read().unwrap().len();
+    let (config, nonce_mode, checkpoints, archive_len, difficulty_bits, cumulative_work) = {
+        let archive = node.archive.read().unwrap();
+        (
+            archive.config,
+            archive.nonce_mode,
+            archive.checkpoints.clone(),
+            archive.len(),
+            archive.required_difficulty(archive.len()),
+            archive.cumulative_work(),
+        )
+    };
     println!("--- Status do Nó ---");
+    println!("Identidade: {}", node.identity.fingerprint());
     println!("Porta TCP: {TCP_PORT}");
     println!("Peers conhecidos: {peers_count}");
     println!("Mensagens no arquivo: {archive_len}");
+    println!(
+        "Mensagens pendentes: {}",
+        node.mempool.lock().unwrap().len()
+    );
+    println!("Função de hash: {}", config.hash_function.name());
+    println!("Dificuldade atual: {difficulty_bits} bits zero iniciais");
+    println!("Códigos de verificação: {}", nonce_mode.name());
+    println!("Trabalho acumulado: {cumulative_work}");
+    if checkpoints.is_empty() {
+        println!("Checkpoints: nenhum");
+    } else {
+        println!(
+            "Checkpoints: {} (último no índice {})",
+            checkpoints.len(),
+            checkpoints.last_index().unwrap_or(0)
+        );
+    }
+    let hashrate = last_hashrate.map_or_else(|| "-".to_string(), format_hashrate);
+    println!("Taxa de hash (última mineração): {hashrate}");
+    if let Some(progress) = current_mining {
+        println!("{}", progress::describe(&progress));
+    }
     println!("--------------------");
 }
 
@@ -129,22 +497,252 @@ fn handle_addpeer(node: &P2PNode, args: &[&str]) {
     }
 }
 
-fn handle_filechat(node: &P2PNode, args: &[&str]) {
-    if args.is_empty() {
-        eprintln!("Uso: filechat <caminho_do_arquivo>");
+fn handle_filechat(node: &P2PNode, channels: &Mutex<ChannelState>, args: &[&str]) {
+    let (file_path, policy) = match args {
+        [path] => (*path, BatchPolicy::Skip),
+        [path, "--stop"] | ["--stop", path] => (*path, BatchPolicy::Stop),
+        _ => {
+            eprintln!("Uso: filechat <caminho_do_arquivo> [--stop]");
+            return;
+        }
+    };
+
+    let file = match fs::File::open(file_path) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Erro ao abrir o arquivo '{file_path}': {e}");
+            return;
+        }
+    };
+
+    let (line_numbers, messages): (Vec<usize>, Vec<String>) = io::BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim_end_matches('\r').to_string()))
+        .filter(|(_, line)| !line.trim().is_empty())
+        .unzip();
+    if messages.is_empty() {
+        println!("Nenhuma mensagem encontrada em '{file_path}'.");
+        return;
+    }
+
+    let channel = channels.lock().unwrap().current().to_string();
+    let indicator = MiningIndicator::start();
+    indicator.set_label(format!("[0/{}] ", messages.len()));
+    let report = node.archive.write().unwrap().add_messages(
+        &messages,
+        &channel,
+        &node.identity,
+        policy,
+        |done, total| indicator.set_label(format!("[{done}/{total}] ")),
+    );
+    drop(indicator);
+
+    for (position, error) in &report.rejected {
+        eprintln!(
+            "Mensagem da linha {} rejeitada: {error}.",
+            line_numbers[*position]
+        );
+    }
+    if report.stopped {
+        eprintln!("Envio interrompido na primeira mensagem inválida.");
+    }
+
+    let peers = if report.mined > 0 {
+        node.announce_archive()
+    } else {
+        0
+    };
+    println!(
+        "{} de {} mensagem(ns) minerada(s); arquivo enviado a {peers} peer(s).",
+        report.mined,
+        messages.len()
+    );
+}
+
+fn handle_attach(node: &P2PNode, channels: &Mutex<ChannelState>, args: &[&str]) {
+    let [path] = args else {
+        eprintln!("Uso: attach <arquivo>");
         return;
+    };
+
+    let manifest = match node.chunks.store_file(path) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            eprintln!("Erro ao ler o arquivo '{path}': {e}");
+            return;
+        }
+    };
+
+    let channel = channels.lock().unwrap().current().to_string();
+    let id = manifest.id();
+    let published = with_indicator(|| {
+        let mut archive = node.archive.write().unwrap();
+        archive.add_attachment(manifest, &channel, &node.identity)
+    });
+    if published {
+        println!("Anexo '{path}' publicado com id {id}.");
     }
+}
 
-    let file_path = args[0];
-    match std::fs::File::open(file_path) {
-        Ok(file) => {
-            let reader = io::BufReader::new(file);
-            for msg in reader.lines().map_while(Result::ok) {
-                handle_chat(node, &[&msg]);
+fn handle_download(node: &P2PNode, args: &[&str]) {
+    let (id, target) = match args {
+        [id] => (*id, None),
+        [id, target] => (*id, Some(target.to_string())),
+        _ => {
+            eprintln!("Uso: download <índice|id> [destino]");
+            return;
+        }
+    };
+
+    let found = node
+        .archive
+        .read()
+        .unwrap()
+        .find_attachment(id)
+        .map(|(index, manifest)| (index, manifest.clone()));
+    let Some((index, manifest)) = found else {
+        eprintln!("Anexo não encontrado: '{id}'.");
+        return;
+    };
+
+    let target = target.unwrap_or_else(|| {
+        Path::new(&manifest.name)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("anexo-{}", manifest.id()))
+    });
+    if Path::new(&target).exists() {
+        eprintln!("O arquivo '{target}' já existe.");
+        return;
+    }
+
+    let missing = node.chunks.missing_chunks(&manifest);
+    if !missing.is_empty() {
+        let events = node.subscribe();
+        let peers = node.request_chunks(&missing);
+        if peers == 0 {
+            eprintln!(
+                "Faltam {} pedaço(s) do anexo e nenhum peer está conectado.",
+                missing.len()
+            );
+            return;
+        }
+
+        println!("Buscando {} pedaço(s) em {peers} peer(s)...", missing.len());
+        let deadline = Instant::now() + DOWNLOAD_TIMEOUT;
+        let mut remaining: HashSet<[u8; 32]> = missing.into_iter().collect();
+        remaining.retain(|hash| !node.chunks.contains(hash));
+        while !remaining.is_empty() {
+            match events.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(NodeEvent::ChunkReceived { hash }) => {
+                    remaining.remove(&hash);
+                }
+                Ok(_) => {}
+                Err(_) => break,
             }
         }
+    }
+
+    match node
+        .chunks
+        .assemble(&manifest)
+        .and_then(|data| fs::write(&target, data))
+    {
+        Ok(()) => println!(
+            "Anexo [{index}] salvo em '{target}' ({} bytes).",
+            manifest.size
+        ),
+        Err(e) => eprintln!("Erro ao baixar o anexo [{index}]: {e}"),
+    }
+}
+
+fn handle_export(node: &P2PNode, args: &[&str]) {
+    let (path, format) = match parse_file_args(args) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{e}");
+            eprintln!("Uso: export <arquivo> [--format json|csv|raw]");
+            return;
+        }
+    };
+
+    let archive = node.archive.read().unwrap();
+    match export::save_archive(path, &archive, format) {
+        Ok(()) => println!("{} mensagens exportadas para '{path}'.", archive.len()),
+        Err(e) => eprintln!("Erro ao exportar para '{path}': {e}"),
+    }
+}
+
+fn handle_import(node: &P2PNode, args: &[&str]) {
+    let (path, format) = match parse_file_args(args) {
+        Ok(parsed) => parsed,
         Err(e) => {
-            eprintln!("Erro ao abrir o arquivo '{file_path}': {e}");
+            eprintln!("{e}");
+            eprintln!("Uso: import <arquivo> [--format json|csv|raw]");
+            return;
+        }
+    };
+
+    let config = node.archive.read().unwrap().config;
+    match export::load_archive(path, format, config) {
+        Ok(archive) => {
+            let len = archive.len();
+            let conflict = node
+                .archive
+                .read()
+                .unwrap()
+                .checkpoints
+                .first_conflict(&archive.chats);
+            if let Some(index) = conflict {
+                eprintln!(
+                    "Erro ao importar '{path}': o histórico não confere com o checkpoint no índice {index}."
+                );
+                return;
+            }
+            if node.adopt_archive(archive) {
+                println!("Arquivo de chats importado com {len} mensagens.");
+            } else {
+                println!(
+                    "O arquivo importado ({len} mensagens) tem menos trabalho acumulado que o atual; mantendo o histórico local."
+                );
+            }
+        }
+        Err(e) => eprintln!("Erro ao importar '{path}': {e}"),
+    }
+}
+
+fn handle_mute(muted: bool) {
+    console::set_muted(muted);
+    if muted {
+        println!("Notificações de novas mensagens desativadas.");
+    } else {
+        println!("Notificações de novas mensagens ativadas.");
+    }
+}
+
+fn chat_notifier(node: &P2PNode, channels: &Mutex<ChannelState>, events: Receiver<NodeEvent>) {
+    for event in events {
+        match event {
+            NodeEvent::ChatsReceived { first_index, chats } => {
+                let archive = node.archive.read().unwrap();
+                let view = HistoryView::new(&archive, &node.identity);
+                let channels = channels.lock().unwrap();
+                for (offset, chat) in chats.iter().enumerate() {
+                    if !channels.is_joined(chat.channel()) {
+                        continue;
+                    }
+                    console::print_async(&format!(
+                        "[{}] ({}) {}",
+                        first_index + offset,
+                        to_hex(&chat.hash[..6]),
+                        view.render(first_index + offset, chat)
+                    ));
+                }
+            }
+            NodeEvent::ChunkReceived { .. } => {}
         }
     }
 }
@@ -152,11 +750,34 @@ fn handle_filechat(node: &P2PNode, args: &[&str]) {
 fn print_help() {
     println!("\nComandos disponíveis:");
     println!("  chat <mensagem>         - Minera e envia uma nova mensagem");
-    println!("  history                 - Lista todo o histórico de chats");
+    println!("  post <mensagem>         - Envia sem minerar, para um nó minerador incluir");
+    println!("  mempool                 - Lista as mensagens pendentes de mineração");
+    println!("  history [a..b] [--last n] [--channel canal] [--thread i] [-r] [-v]");
+    println!("                          - Lista o histórico (intervalo, últimas n, canal, hash)");
+    println!("  search <regex> [-v]     - Busca mensagens do histórico por expressão regular");
+    println!("  nick <apelido>          - Registra um apelido para a sua chave");
+    println!("  reply <índice> <mensagem>");
+    println!("                          - Responde a uma mensagem do histórico");
+    println!("  react <índice> <reação> - Reage a uma mensagem (ex.: react 3 👍)");
+    println!("  retract <índice>        - Retira uma mensagem sua (o histórico passa a ocultá-la)");
+    println!("  dm <destinatário> <mensagem>");
+    println!("                          - Envia uma mensagem direta cifrada ao destinatário");
+    println!("  join <canal>            - Entra em um canal e passa a enviar mensagens para ele");
+    println!("  leave [canal]           - Sai de um canal (padrão: o canal atual)");
+    println!("  channels                - Lista os canais conhecidos");
     println!("  peers                   - Mostra os peers conectados e conhecidos");
     println!("  status                  - Exibe o status geral do nó");
     println!("  addpeer <ip>            - Adiciona e conecta a um novo peer pelo IP");
-    println!("  filechat <arquivo>      - Envia mensagens de um arquivo texto");
+    println!("  filechat <arquivo> [--stop]");
+    println!("                          - Minera as mensagens de um arquivo texto, uma por linha");
+    println!("  attach <arquivo>        - Publica um arquivo como anexo no canal atual");
+    println!("  download <índice|id> [destino]");
+    println!("                          - Baixa dos peers, verifica e salva um anexo");
+    println!("  export <arquivo> [--format json|csv|raw]");
+    println!("                          - Exporta o histórico de chats para um arquivo");
+    println!("  import <arquivo> [--format json|csv|raw]");
+    println!("                          - Importa e verifica um histórico de chats");
+    println!("  mute / unmute           - Desativa/ativa o aviso de novas mensagens");
     println!("  help                    - Mostra esta ajuda");
     println!("  quit                    - Sai do programa\n");
 }
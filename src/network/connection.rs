@@ -0,0 +1,44 @@
+use std::io::{self, Write};
+use std::net::{Shutdown, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const WRITE_TIMEOUT: Duration = Duration::from_secs(30);
+
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Clone)]
+pub struct Connection {
+    id: u64,
+    ip: u32,
+    stream: Arc<Mutex<TcpStream>>,
+}
+
+impl Connection {
+    pub fn new(stream: &TcpStream, ip: u32) -> io::Result<Self> {
+        let writer = stream.try_clone()?;
+        writer.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        Ok(Connection {
+            id: NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed),
+            ip,
+            stream: Arc::new(Mutex::new(writer)),
+        })
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn ip(&self) -> u32 {
+        self.ip
+    }
+
+    pub fn send(&self, frame: &[u8]) -> bool {
+        self.stream.lock().unwrap().write_all(frame).is_ok()
+    }
+
+    pub fn close(&self) {
+        let _ = self.stream.lock().unwrap().shutdown(Shutdown::Both);
+    }
+}
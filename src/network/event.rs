@@ -0,0 +1,12 @@
+use crate::core::message::Chat;
+
+#[derive(Debug, Clone)]
+pub enum NodeEvent {
+    ChatsReceived {
+        first_index: usize,
+        chats: Vec<Chat>,
+    },
+    ChunkReceived {
+        hash: [u8; 32],
+    },
+}
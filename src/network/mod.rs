@@ -1,3 +1,6 @@
+pub mod connection;
+pub mod event;
 pub mod node;
 pub mod peer;
+pub use event::NodeEvent;
 pub use node::P2PNode;
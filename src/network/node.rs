@@ -1,27 +1,69 @@
+use super::connection::Connection;
+use super::event::NodeEvent;
 use super::peer::PeerList;
-use crate::constants::TCP_PORT;
-use crate::core::{archive::Archive, message::MessageType};
+use crate::constants::{DEFAULT_CHUNK_DIR, TCP_PORT};
+use crate::core::archive::Archive;
+use crate::core::attachment::{ChunkStore, content_hash};
+use crate::core::config::ChainConfig;
+use crate::core::hex::to_hex;
+use crate::core::identity::Identity;
+use crate::core::mempool::Mempool;
+use crate::core::message::{CHUNK_SIZE, Chat, MessageType};
 use crate::logger;
 
-use std::io::{Read, Write};
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::mem;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 use std::time::Duration;
 
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
 pub struct P2PNode {
     pub peers: Arc<Mutex<PeerList>>,
     pub archive: Arc<RwLock<Archive>>,
+    pub identity: Arc<Identity>,
+    pub chunks: Arc<ChunkStore>,
+    pub mempool: Arc<Mutex<Mempool>>,
+    requested_chunks: Arc<Mutex<HashSet<[u8; 32]>>>,
+    connections: Arc<Mutex<HashMap<u64, Connection>>>,
+    subscribers: Arc<Mutex<Vec<Sender<NodeEvent>>>>,
+    network_id: u32,
+    genesis_id: [u8; 32],
 }
 
 impl P2PNode {
-    pub fn new() -> Self {
+    pub fn new(config: ChainConfig, identity: Identity) -> Self {
         P2PNode {
             peers: Arc::new(Mutex::new(PeerList::new())),
-            archive: Arc::new(RwLock::new(Archive::new())),
+            archive: Arc::new(RwLock::new(Archive::new(config))),
+            identity: Arc::new(identity),
+            chunks: Arc::new(ChunkStore::new(DEFAULT_CHUNK_DIR)),
+            mempool: Arc::new(Mutex::new(Mempool::new())),
+            requested_chunks: Arc::new(Mutex::new(HashSet::new())),
+            connections: Arc::new(Mutex::new(HashMap::new())),
+            subscribers: Arc::new(Mutex::new(Vec::new())),
+            network_id: config.network_id,
+            genesis_id: Archive::genesis_id(&config),
         }
     }
 
+    pub fn subscribe(&self) -> Receiver<NodeEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    fn emit(&self, event: NodeEvent) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|tx| tx.send(event.clone()).is_ok());
+    }
+
     pub fn start_listener(&self) {
         let node_arc = Arc::new(self.clone_state());
         thread::spawn(move || {
@@ -50,6 +92,14 @@ impl P2PNode {
         P2PNode {
             peers: Arc::clone(&self.peers),
             archive: Arc::clone(&self.archive),
+            identity: Arc::clone(&self.identity),
+            chunks: Arc::clone(&self.chunks),
+            mempool: Arc::clone(&self.mempool),
+            requested_chunks: Arc::clone(&self.requested_chunks),
+            connections: Arc::clone(&self.connections),
+            subscribers: Arc::clone(&self.subscribers),
+            network_id: self.network_id,
+            genesis_id: self.genesis_id,
         }
     }
 
@@ -71,14 +121,36 @@ impl P2PNode {
             return;
         };
 
+        let connection = match Connection::new(&stream, peer_ip_u32) {
+            Ok(connection) => connection,
+            Err(e) => {
+                logger::warn(&format!("Falha ao preparar conexão com {peer_addr}: {e}"));
+                return;
+            }
+        };
+
+        if let Err(e) = self.handshake(&mut stream, &connection) {
+            logger::warn(&format!("Conexão com {peer_addr} recusada: {e}"));
+            Self::send_notification(&connection, &format!("Conexão recusada: {e}"));
+            connection.close();
+            return;
+        }
+
         self.peers.lock().unwrap().add_peer(peer_ip_u32);
+        for chat in self.mempool.lock().unwrap().chats() {
+            connection.send(&Self::pending_message(chat));
+        }
+        self.connections
+            .lock()
+            .unwrap()
+            .insert(connection.id(), connection.clone());
 
         logger::debug(&format!("Novo peer conectado: {peer_addr}"));
         let node_clone = self.clone_state();
-        let stream_clone = stream.try_clone().expect("Falha ao clonar stream TCP");
+        let requester_connection = connection.clone();
 
         thread::spawn(move || {
-            node_clone.peer_requester_thread(stream_clone);
+            node_clone.peer_requester_thread(requester_connection);
         });
 
         loop {
@@ -90,7 +162,7 @@ impl P2PNode {
                     }
 
                     let msg_type = MessageType::from(msg_type_buf[0]);
-                    if !self.handle_message(msg_type, &mut stream) {
+                    if !self.handle_message(msg_type, &mut stream, &connection) {
                         break;
                     }
                 }
@@ -101,51 +173,115 @@ impl P2PNode {
             }
         }
 
-        self.peers.lock().unwrap().remove_peer(peer_ip_u32);
+        connection.close();
+        let mut connections = self.connections.lock().unwrap();
+        connections.remove(&connection.id());
+        if !connections.values().any(|other| other.ip() == peer_ip_u32) {
+            self.peers.lock().unwrap().remove_peer(peer_ip_u32);
+        }
     }
 
-    fn peer_requester_thread(&self, mut stream: TcpStream) {
+    fn peer_requester_thread(&self, connection: Connection) {
         loop {
             thread::sleep(Duration::from_secs(5));
 
             logger::debug("Enviando pedido de lista de peers");
-            if stream.write_all(&[MessageType::PeerRequest as u8]).is_err() {
+            if !connection.send(&[MessageType::PeerRequest as u8]) {
                 logger::warn("Falha ao enviar pedido de lista de peers.");
                 break;
             }
 
             logger::debug("Enviando pedido de arquivo de chats");
-            if stream
-                .write_all(&[MessageType::ArchiveRequest as u8])
-                .is_err()
-            {
+            if !connection.send(&[MessageType::ArchiveRequest as u8]) {
                 logger::warn("Falha ao enviar pedido de arquivo de chats.");
                 break;
             }
 
-            if !self.handle_archive_request(&mut stream) {
+            if !self.handle_archive_request(&connection) {
                 logger::warn("Falha ao propagar arquivo de chats para o peer.");
                 break;
             }
         }
     }
 
-    pub fn handle_message(&self, msg_type: MessageType, stream: &mut TcpStream) -> bool {
+    pub fn handle_message(
+        &self,
+        msg_type: MessageType,
+        stream: &mut TcpStream,
+        connection: &Connection,
+    ) -> bool {
         match msg_type {
-            MessageType::PeerRequest => self.handle_peer_request(stream),
+            MessageType::PeerRequest => self.handle_peer_request(connection),
             MessageType::PeerResponse => self.handle_peer_response(stream),
-            MessageType::ArchiveRequest => self.handle_archive_request(stream),
-            MessageType::ArchiveResponse => self.handle_archive_response(stream),
+            MessageType::ArchiveRequest => self.handle_archive_request(connection),
+            MessageType::ArchiveResponse => self.handle_archive_response(stream, connection),
             MessageType::NotificationMessage => self.handle_notification_message(stream),
+            MessageType::ChunkRequest => self.handle_chunk_request(stream, connection),
+            MessageType::ChunkResponse => self.handle_chunk_response(stream),
+            MessageType::PendingChat => self.handle_pending_chat(stream),
+            MessageType::Hello => match self.read_hello(stream) {
+                Ok(()) => true,
+                Err(e) => {
+                    logger::warn(&format!("Peer de outra rede: {e}"));
+                    false
+                }
+            },
+        }
+    }
+
+    fn hello_message(&self) -> Vec<u8> {
+        let mut bytes = vec![MessageType::Hello as u8];
+        bytes.extend_from_slice(&self.network_id.to_be_bytes());
+        bytes.extend_from_slice(&self.genesis_id);
+        bytes
+    }
+
+    fn handshake(&self, stream: &mut TcpStream, connection: &Connection) -> Result<(), String> {
+        if !connection.send(&self.hello_message()) {
+            return Err("falha ao enviar Hello".to_string());
         }
+
+        let _ = stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT));
+        let mut msg_type = [0u8; 1];
+        let result = match stream.read_exact(&mut msg_type) {
+            Ok(()) if msg_type[0] == MessageType::Hello as u8 => self.read_hello(stream),
+            Ok(()) => Err("o peer não se identificou com Hello".to_string()),
+            Err(e) => Err(format!("Hello não recebido: {e}")),
+        };
+        let _ = stream.set_read_timeout(None);
+        result
     }
 
-    fn handle_peer_request(&self, stream: &mut TcpStream) -> bool {
+    fn read_hello(&self, stream: &mut TcpStream) -> Result<(), String> {
+        let mut network_id = [0u8; 4];
+        let mut genesis_id = [0u8; 32];
+        stream
+            .read_exact(&mut network_id)
+            .and_then(|_| stream.read_exact(&mut genesis_id))
+            .map_err(|e| format!("Hello incompleto: {e}"))?;
+
+        let network_id = u32::from_be_bytes(network_id);
+        if network_id != self.network_id {
+            return Err(format!(
+                "rede {network_id} diferente da rede local {}",
+                self.network_id
+            ));
+        }
+        if genesis_id != self.genesis_id {
+            return Err(format!(
+                "registro gênese {} diferente do local (parâmetros de rede diferentes)",
+                to_hex(&genesis_id[..8])
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn handle_peer_request(&self, connection: &Connection) -> bool {
         logger::debug("Enviando lista de peers");
-        let peer_list = self.peers.lock().unwrap();
-        let response = peer_list.to_bytes();
+        let response = self.peers.lock().unwrap().to_bytes();
 
-        stream.write_all(&response).is_ok()
+        connection.send(&response)
     }
 
     fn handle_peer_response(&self, stream: &mut TcpStream) -> bool {
@@ -182,16 +318,11 @@ impl P2PNode {
         true
     }
 
-    fn handle_archive_request(&self, stream: &mut TcpStream) -> bool {
+    fn handle_archive_request(&self, connection: &Connection) -> bool {
         logger::debug("Enviando arquivo de chats");
-        let arch = self.archive.read().unwrap();
+        let response = self.archive.read().unwrap().to_bytes();
 
-        if arch.len() > 0 {
-            let response = arch.to_bytes();
-            stream.write_all(&response).is_ok()
-        } else {
-            true
-        }
+        connection.send(&response)
     }
 
     pub fn connect_to_peer(&self, peer_addr: &str) {
@@ -209,50 +340,291 @@ impl P2PNode {
         });
     }
 
-    fn handle_archive_response(&self, stream: &mut TcpStream) -> bool {
+    fn handle_archive_response(&self, stream: &mut TcpStream, connection: &Connection) -> bool {
         logger::debug("Recebendo arquivo de chats");
-        let mut full_data = vec![MessageType::ArchiveResponse as u8];
         let mut count_buf = [0u8; 4];
 
         if stream.read_exact(&mut count_buf).is_err() {
             return false;
         }
 
-        full_data.extend_from_slice(&count_buf);
-
         let count = u32::from_be_bytes(count_buf) as usize;
+        let mut new_archive = {
+            let current_archive = self.archive.read().unwrap();
+            let mut archive = Archive::with_chats(Vec::new(), current_archive.config);
+            archive.checkpoints = current_archive.checkpoints.clone();
+            archive
+        };
+        let hash_len = new_archive.config.hash_function.digest_len();
 
         for _ in 0..count {
-            let mut len_buf = [0u8; 1];
+            match Chat::read_from(stream, hash_len) {
+                Ok(chat) => new_archive.chats.push(chat),
+                Err(e) => {
+                    logger::warn(&format!("Falha ao ler chat do arquivo recebido: {e}"));
+                    return false;
+                }
+            }
+        }
 
-            if stream.read_exact(&mut len_buf).is_err() {
-                return false;
+        if !Self::would_adopt(&self.archive.read().unwrap(), &new_archive) {
+            return true;
+        }
+
+        match new_archive.validate() {
+            Ok(()) => {
+                self.adopt_archive(new_archive);
+                true
+            }
+            Err(e) => {
+                logger::warn(&format!("Arquivo de chats recebido rejeitado: {e}"));
+                Self::send_notification(connection, &format!("Arquivo de chats rejeitado: {e}"))
+            }
+        }
+    }
+
+    fn send_notification(connection: &Connection, text: &str) -> bool {
+        let mut end = text.len().min(u8::MAX as usize);
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        let mut bytes = vec![MessageType::NotificationMessage as u8, end as u8];
+        bytes.extend_from_slice(&text.as_bytes()[..end]);
+
+        connection.send(&bytes)
+    }
+
+    fn would_adopt(current_archive: &Archive, new_archive: &Archive) -> bool {
+        if !new_archive.is_better_than(current_archive) {
+            return false;
+        }
+
+        if let Some(index) = current_archive
+            .checkpoints
+            .first_conflict(&new_archive.chats)
+        {
+            logger::warn(&format!(
+                "Arquivo de chats recusado: não confere com o checkpoint no índice {index}"
+            ));
+            return false;
+        }
+
+        true
+    }
+
+    pub fn adopt_archive(&self, new_archive: Archive) -> bool {
+        let mut current_archive = self.archive.write().unwrap();
+        if !Self::would_adopt(&current_archive, &new_archive) {
+            return false;
+        }
+
+        let first_index = current_archive.common_prefix_len(&new_archive);
+        let nonce_mode = current_archive.nonce_mode;
+        let fixed_timestamp = current_archive.fixed_timestamp;
+        let checkpoints = mem::take(&mut current_archive.checkpoints);
+        *current_archive = new_archive;
+        current_archive.nonce_mode = nonce_mode;
+        current_archive.fixed_timestamp = fixed_timestamp;
+        current_archive.checkpoints = checkpoints;
+        current_archive.update_checkpoints();
+        self.mempool.lock().unwrap().prune(&current_archive);
+        logger::info(&format!(
+            "Arquivo de chats atualizado com {} mensagens.",
+            current_archive.len()
+        ));
+
+        self.emit(NodeEvent::ChatsReceived {
+            first_index,
+            chats: current_archive.chats[first_index..].to_vec(),
+        });
+
+        true
+    }
+
+    fn broadcast(&self, bytes: &[u8]) -> usize {
+        let connections: Vec<Connection> =
+            self.connections.lock().unwrap().values().cloned().collect();
+        let failed: Vec<u64> = connections
+            .iter()
+            .filter(|connection| !connection.send(bytes))
+            .map(Connection::id)
+            .collect();
+
+        let mut connections = self.connections.lock().unwrap();
+        for id in failed {
+            if let Some(connection) = connections.remove(&id) {
+                connection.close();
+            }
+        }
+        connections
+            .values()
+            .map(Connection::ip)
+            .collect::<HashSet<u32>>()
+            .len()
+    }
+
+    pub fn announce_archive(&self) -> usize {
+        let response = self.archive.read().unwrap().to_bytes();
+        self.broadcast(&response)
+    }
+
+    pub fn request_chunks(&self, hashes: &[[u8; 32]]) -> usize {
+        self.requested_chunks
+            .lock()
+            .unwrap()
+            .extend(hashes.iter().copied());
+
+        let mut requests = Vec::new();
+        for hash in hashes {
+            requests.push(MessageType::ChunkRequest as u8);
+            requests.extend_from_slice(hash);
+        }
+        self.broadcast(&requests)
+    }
+
+    fn pending_message(chat: &Chat) -> Vec<u8> {
+        let mut bytes = vec![MessageType::PendingChat as u8];
+        bytes.extend_from_slice(&chat.unmined_bytes());
+        bytes
+    }
+
+    pub fn submit_pending(&self, chat: Chat) -> Result<usize, String> {
+        let content_hash = chat.content_hash();
+        {
+            let archive = self.archive.read().unwrap();
+            if archive.contains_content(&content_hash) {
+                return Err("a mensagem já está no arquivo de chats".to_string());
             }
+            archive.validate_pending(&chat).map_err(|e| e.to_string())?;
+        }
 
-            full_data.push(len_buf[0]);
+        let message = Self::pending_message(&chat);
+        if !self.mempool.lock().unwrap().insert(chat) {
+            return Err("a mensagem já está na mempool".to_string());
+        }
 
-            let msg_len = len_buf[0] as usize;
-            let mut chat_data = vec![0u8; msg_len + 32];
+        Ok(self.broadcast(&message))
+    }
 
-            if stream.read_exact(&mut chat_data).is_err() {
+    fn handle_pending_chat(&self, stream: &mut TcpStream) -> bool {
+        let chat = match Chat::read_unmined(stream) {
+            Ok(chat) => chat,
+            Err(e) => {
+                logger::warn(&format!("Falha ao ler mensagem pendente: {e}"));
                 return false;
             }
+        };
 
-            full_data.extend_from_slice(&chat_data);
+        match self.submit_pending(chat) {
+            Ok(peers) => logger::debug(&format!(
+                "Mensagem pendente aceita e repassada a {peers} peer(s)"
+            )),
+            Err(e) => logger::debug(&format!("Mensagem pendente ignorada: {e}")),
         }
 
-        if let Some(new_archive) = Archive::from_bytes(&full_data) {
-            if new_archive.is_valid() {
-                let mut current_archive = self.archive.write().unwrap();
+        true
+    }
 
-                if new_archive.len() > current_archive.len() {
-                    *current_archive = new_archive;
-                    logger::info(&format!(
-                        "Arquivo de chats atualizado com {} mensagens.",
-                        current_archive.len()
-                    ));
+    pub fn start_miner(&self) {
+        let node = self.clone_state();
+        thread::spawn(move || {
+            loop {
+                let Some(chat) = node.mempool.lock().unwrap().pop() else {
+                    thread::sleep(Duration::from_secs(1));
+                    continue;
+                };
+
+                let (template, mut nonce) = {
+                    let archive = node.archive.read().unwrap();
+                    if archive.contains_content(&chat.content_hash()) {
+                        continue;
+                    }
+                    if let Err(e) = archive.validate_pending(&chat) {
+                        logger::warn(&format!("Mensagem pendente descartada: {e}"));
+                        continue;
+                    }
+
+                    let template = archive.mining_template();
+                    let nonce = archive.nonce_mode.source(template.index());
+                    (template, nonce)
+                };
+
+                let mined = template.solve(chat.clone(), nonce.as_mut());
+                let mut archive = node.archive.write().unwrap();
+                if !archive.push_mined(&template, mined) {
+                    logger::info("O arquivo de chats mudou durante a mineração; tentando de novo.");
+                    drop(archive);
+                    node.mempool.lock().unwrap().insert(chat);
+                    continue;
                 }
+
+                node.emit(NodeEvent::ChatsReceived {
+                    first_index: template.index(),
+                    chats: vec![archive.chats[template.index()].clone()],
+                });
+            }
+        });
+    }
+
+    fn handle_chunk_request(&self, stream: &mut TcpStream, connection: &Connection) -> bool {
+        let mut hash = [0u8; 32];
+        if stream.read_exact(&mut hash).is_err() {
+            return false;
+        }
+
+        let mut response = vec![MessageType::ChunkResponse as u8];
+        response.extend_from_slice(&hash);
+        match self.chunks.get(&hash) {
+            Some(data) => {
+                logger::debug(&format!("Enviando pedaço {}", to_hex(&hash[..4])));
+                response.extend_from_slice(&(data.len() as u32).to_be_bytes());
+                response.extend_from_slice(&data);
             }
+            None => response.extend_from_slice(&0u32.to_be_bytes()),
+        }
+
+        connection.send(&response)
+    }
+
+    fn handle_chunk_response(&self, stream: &mut TcpStream) -> bool {
+        let mut hash = [0u8; 32];
+        let mut len_buf = [0u8; 4];
+        if stream.read_exact(&mut hash).is_err() || stream.read_exact(&mut len_buf).is_err() {
+            return false;
+        }
+
+        let len = u32::from_be_bytes(len_buf) as usize;
+        if len > CHUNK_SIZE {
+            logger::warn(&format!("Pedaço recebido com {len} bytes excede o limite."));
+            return false;
+        }
+
+        let mut data = vec![0u8; len];
+        if stream.read_exact(&mut data).is_err() {
+            return false;
+        }
+
+        if len == 0 {
+            logger::debug(&format!("Peer não possui o pedaço {}", to_hex(&hash[..4])));
+        } else if !self.requested_chunks.lock().unwrap().contains(&hash) {
+            logger::debug(&format!(
+                "Pedaço {} não solicitado ou já recebido; descartado.",
+                to_hex(&hash[..4])
+            ));
+        } else if content_hash(&data) != hash {
+            logger::warn(&format!(
+                "Pedaço {} recebido não confere com o hash; descartado.",
+                to_hex(&hash[..4])
+            ));
+        } else if let Err(e) = self.chunks.put(&data) {
+            logger::warn(&format!(
+                "Falha ao salvar pedaço {}: {e}",
+                to_hex(&hash[..4])
+            ));
+        } else {
+            self.requested_chunks.lock().unwrap().remove(&hash);
+            self.emit(NodeEvent::ChunkReceived { hash });
         }
 
         true